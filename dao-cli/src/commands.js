@@ -11,7 +11,15 @@ function setupCommands() {
                 description: { type: 'string', describe: 'Description of the proposal', demandOption: true },
                 deadline: { type: 'string', describe: 'Deadline for the proposal as timestamp', demandOption: true },
                 options: { type: 'string', describe: 'Comma-separated options for the proposal', demandOption: true },
-                minimumVotes: { type: 'string', describe: 'Minimum votes required', demandOption: true }
+                minimumVotes: { type: 'string', describe: 'Minimum votes required', demandOption: true },
+                link: { type: 'string', describe: 'IPFS CID or URL of the full proposal text' },
+                contentFile: { type: 'string', describe: 'File with the full proposal text, its sha256 is stored on-chain' },
+                category: { type: 'string', describe: 'Category of the proposal, must be allowed by the proposal contract' },
+                tags: { type: 'string', describe: 'Comma-separated tags for the proposal' },
+                commitDeadline: { type: 'string', describe: 'Use commit-reveal voting, commits close at this timestamp' },
+                optimistic: { type: 'boolean', describe: 'Pass at the deadline unless objections escalate it to a vote' },
+                weights: { type: 'string', describe: 'Token weighted voting, comma-separated token:decimals:multiplier entries. Balances are not locked, tokens moved after voting can vote again' },
+                reputation: { type: 'boolean', describe: 'Weigh ballots by reputation, plus the single --weights entry if given' }
             });
         }, async (argv) => {
             await nearClient.createProposal(argv);
@@ -21,6 +29,11 @@ function setupCommands() {
         }, async (argv) => {
             await nearClient.finalizeProposal(argv.proposalId);
         })
+        .command('finalize-expired', 'Finalize proposals whose deadline has passed', (yargs) => {
+            return yargs.option('limit', { type: 'string', describe: 'Maximum number of proposals to finalize', default: '20' });
+        }, async (argv) => {
+            await nearClient.finalizeExpired(argv.limit);
+        })
         .help()
         .alias('help', 'h')
         .argv;
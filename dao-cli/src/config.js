@@ -15,5 +15,8 @@ module.exports = {
     helperUrl: process.env.HELPER_URL,
     explorerUrl: process.env.EXPLORER_URL,
     adminAccountId: process.env.ADMIN_ACCOUNT_ID,
-    daoContractId: process.env.DAO_CONTRACT_ID
+    daoContractId: process.env.DAO_CONTRACT_ID,
+    proposalContractId: process.env.PROPOSAL_CONTRACT_ID,
+    // yoctoNEAR attached to create_proposal for storage, defaults to 0.1 NEAR
+    proposalStorageDeposit: process.env.PROPOSAL_STORAGE_DEPOSIT || "100000000000000000000000"
 };
@@ -1,4 +1,6 @@
 // src/near.client.js
+const crypto = require("crypto");
+const fs = require("fs");
 const nearAPI = require("near-api-js");
 const config = require("./config");
 
@@ -13,8 +15,38 @@ const connectionConfig = {
     explorerUrl: config.explorerUrl,
 };
 
+// Parse the token:decimals:multiplier entries of --weights
+function parseWeightedSources(weights) {
+    return weights.split(',').map((entry) => {
+        const [tokenContractId, decimals, multiplier] = entry.split(':');
+        return {
+            token_contract_id: tokenContractId,
+            decimals: parseInt(decimals, 10),
+            multiplier: parseInt(multiplier, 10)
+        };
+    });
+}
+
+function votingWeight(weights, reputation) {
+    const sources = weights ? parseWeightedSources(weights) : [];
+    if (reputation) {
+        if (sources.length > 1) {
+            throw new Error('Reputation voting combines with at most one token');
+        }
+        return { Reputation: { balance: sources[0] || null } };
+    }
+    return sources.length > 0 ? { TokenWeighted: { sources } } : "OnePerVoter";
+}
+
+// Nanosecond timestamps overflow a JS number, so BigInt values are written out as bare JSON integers
+function stringifyArgs(args) {
+    const json = JSON.stringify(args, (key, value) =>
+        typeof value === 'bigint' ? `__bigint__${value}` : value);
+    return Buffer.from(json.replace(/"__bigint__(\d+)"/g, '$1'));
+}
+
 // Function to create a proposal 
-async function createProposal({ title, description, deadline, options, minimumVotes }) {
+async function createProposal({ title, description, deadline, options, minimumVotes, link, contentFile, category, tags, commitDeadline, optimistic, weights, reputation }) {
     try {
         const near = await connect(connectionConfig);
         const account = await near.account(config.adminAccountId);
@@ -22,18 +54,30 @@ async function createProposal({ title, description, deadline, options, minimumVo
         const args = {
             title,
             description,
-            deadline: parseInt(deadline, 10),
+            deadline: BigInt(deadline),
             options_vec: options.split(','),
-            minimum_votes: parseInt(minimumVotes, 10)
+            minimum_votes: parseInt(minimumVotes, 10),
+            link: link || null,
+            // The contract expects the sha256 of the full text as base64
+            content_hash: contentFile
+                ? crypto.createHash('sha256').update(fs.readFileSync(contentFile)).digest('base64')
+                : null,
+            category: category || null,
+            tags: tags ? tags.split(',') : [],
+            voting_mode: commitDeadline
+                ? { CommitReveal: { commit_deadline: BigInt(commitDeadline) } }
+                : (optimistic ? "Optimistic" : "Open"),
+            voting_weight: votingWeight(weights, reputation)
         };
 
         console.log("Sending createProposal transaction...");
         const result = await account.functionCall({
             contractId: config.daoContractId,
             methodName: "create_proposal",
-            args,
+            args: stringifyArgs(args),
             gas: "300000000000000",
-            attachedDeposit: "0"
+            // Covers the proposal storage, the unused part is refunded
+            attachedDeposit: config.proposalStorageDeposit
         });
 
         console.log('Transaction successful:', result.transaction.hash);
@@ -46,7 +90,7 @@ async function createProposal({ title, description, deadline, options, minimumVo
 // Function to finalize a proposal 
 async function finalizeProposal(proposalId) {
     try {
-        const near = await connectToNear();
+        const near = await connect(connectionConfig);
         const account = await near.account(config.adminAccountId);
 
         console.log("Sending finalizeProposal transaction...");
@@ -59,10 +103,33 @@ async function finalizeProposal(proposalId) {
         });
 
         console.log('Transaction successful:', result.transaction.hash);
+        console.log('Final state:', nearAPI.providers.getTransactionLastResult(result));
     } catch (error) {
         console.error('Failed to finalize proposal:', error);
         throw error;
     }
 }
 
-module.exports = { createProposal, finalizeProposal };
+// Function to finalize every proposal whose deadline has passed, meant for a cron-style keeper
+async function finalizeExpired(limit) {
+    try {
+        const near = await connect(connectionConfig);
+        const account = await near.account(config.adminAccountId);
+
+        console.log("Sending finalizeExpired transaction...");
+        const result = await account.functionCall({
+            contractId: config.proposalContractId,
+            methodName: "finalize_expired",
+            args: { limit: parseInt(limit, 10) },
+            gas: "300000000000000",
+            attachedDeposit: "0"
+        });
+
+        console.log('Transaction successful:', result.transaction.hash);
+    } catch (error) {
+        console.error('Failed to finalize expired proposals:', error);
+        throw error;
+    }
+}
+
+module.exports = { createProposal, finalizeProposal, finalizeExpired };
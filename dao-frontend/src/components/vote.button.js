@@ -2,6 +2,10 @@ import React, { useState } from 'react';
 import { useNearWallet } from '../contexts/near.context';
 import { useAlert } from '../contexts/alert.context';
 
+const VOTE_GAS = '300000000000000';
+// 0.01 NEAR for the ballot storage, the unused part is refunded
+const VOTE_STORAGE_DEPOSIT = '10000000000000000000000';
+
 const VoteComponent = ({ proposalId, optionIndex, optionText }) => {
   const { wallet, contract } = useNearWallet();
   const { showAlert, hideAlert } = useAlert();
@@ -15,10 +19,14 @@ const VoteComponent = ({ proposalId, optionIndex, optionText }) => {
     const accountId = wallet.getAccountId();
 
     try {
-      await contract.vote({ 
-        proposal_id: parseInt(proposalId, 10), 
-        vote_option: optionIndex,
-        voter: accountId 
+      await contract.vote({
+        args: {
+          proposal_id: parseInt(proposalId, 10),
+          vote_option: optionIndex,
+          voter: accountId
+        },
+        gas: VOTE_GAS,
+        amount: VOTE_STORAGE_DEPOSIT
       });
       showAlert(`Vote successful for option: ${optionText}`, 'success');
     } catch (error) {
@@ -0,0 +1,117 @@
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, AccountId};
+use crate::events::emit_event;
+use crate::{DAO, DAOExt};
+
+#[near_bindgen]
+impl DAO {
+    // First step of an admin transfer, the nominee takes over once they call `accept_admin`
+    pub fn propose_new_admin(&mut self, account: AccountId) {
+        self.assert_admin("Only the admin can propose a new admin");
+        emit_event("admin_proposed", json!({ "admin": self.admin, "nominee": account }));
+        self.pending_admin = Some(account);
+    }
+
+    pub fn accept_admin(&mut self) {
+        let nominee = env::predecessor_account_id();
+        assert!(self.pending_admin.as_ref() == Some(&nominee), "Only the nominated account can accept the admin role");
+        self.pending_admin = None;
+        self.internal_set_admin(Some(nominee));
+    }
+
+    // Give up the admin role for good, leaving the DAO to its council
+    pub fn renounce_admin(&mut self) {
+        assert!(
+            self.admin.as_ref() == Some(&env::predecessor_account_id()),
+            "Only the admin can renounce the admin role"
+        );
+        assert!(self.council.is_some(), "Set a council before renouncing the admin role");
+        self.pending_admin = None;
+        emit_event("admin_renounced", json!({ "admin": self.admin }));
+        self.admin = None;
+    }
+
+    pub fn get_pending_admin(&self) -> Option<AccountId> {
+        self.pending_admin.clone()
+    }
+}
+
+impl DAO {
+    pub(crate) fn internal_set_admin(&mut self, admin: Option<AccountId>) {
+        emit_event("admin_changed", json!({ "old_admin": self.admin, "new_admin": admin }));
+        self.admin = admin;
+    }
+}
+
+// The rest of this file holds the inline tests for the code above
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Council;
+    use near_sdk::test_utils::{get_logs, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    fn set_caller(account: &str) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("dao".parse().unwrap())
+            .predecessor_account_id(account.parse().unwrap())
+            .build());
+    }
+
+    fn setup_dao() -> DAO {
+        set_caller("admin");
+        DAO::new("admin".parse().unwrap(), "proposal".parse().unwrap())
+    }
+
+    #[test]
+    fn test_two_step_admin_transfer() {
+        let mut contract = setup_dao();
+        contract.propose_new_admin("bob".parse().unwrap());
+        assert_eq!(contract.get_admin_id(), Some("admin".parse().unwrap()));
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"admin_proposed\"")));
+
+        set_caller("bob");
+        contract.accept_admin();
+        assert_eq!(contract.get_admin_id(), Some("bob".parse().unwrap()));
+        assert_eq!(contract.get_pending_admin(), None);
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"admin_changed\"")));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the nominated account can accept the admin role")]
+    fn test_accept_admin_not_nominee() {
+        let mut contract = setup_dao();
+        contract.propose_new_admin("bob".parse().unwrap());
+        set_caller("mallory");
+        contract.accept_admin();
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the admin can propose a new admin")]
+    fn test_propose_new_admin_not_admin() {
+        let mut contract = setup_dao();
+        set_caller("mallory");
+        contract.propose_new_admin("mallory".parse().unwrap());
+    }
+
+    #[test]
+    fn test_renounce_admin() {
+        let mut contract = setup_dao();
+        contract.set_council(Council {
+            members: vec!["alice".parse().unwrap()],
+            threshold: 1,
+            action_expiry: 1_000,
+        });
+        contract.renounce_admin();
+        assert_eq!(contract.get_admin_id(), None);
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"admin_renounced\"")));
+    }
+
+    #[test]
+    #[should_panic(expected = "Set a council before renouncing the admin role")]
+    fn test_renounce_admin_without_council() {
+        let mut contract = setup_dao();
+        contract.renounce_admin();
+    }
+}
@@ -0,0 +1,349 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId};
+use crate::{GasConfig, DAO, DAOExt};
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+// Expired actions dropped by each `propose_action`, `purge_expired_actions` takes care of larger backlogs
+const PRUNE_BATCH: u64 = 10;
+
+// M-of-N council that replaces the single admin for sensitive operations once configured
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Council {
+    pub members: Vec<AccountId>,
+    // Distinct approvals an action needs before it runs
+    pub threshold: u8,
+    // How long a pending action can collect approvals, in nanoseconds
+    pub action_expiry: u64,
+}
+
+impl Council {
+    fn assert_valid(&self) {
+        assert!(!self.members.is_empty(), "Council needs at least one member");
+        assert!(
+            self.threshold > 0 && self.threshold as usize <= self.members.len(),
+            "Threshold must be between 1 and the number of council members"
+        );
+        for (index, member) in self.members.iter().enumerate() {
+            assert!(!self.members[..index].contains(member), "Duplicate council member: {}", member);
+        }
+    }
+}
+
+// Operations that need council approval
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum CouncilAction {
+    // Let `proposer` create one proposal through the DAO, they pay for it and get the refunds
+    AllowProposal { proposer: AccountId },
+    FinalizeProposal { proposal_id: u64 },
+    SetGasConfig { gas_config: GasConfig },
+    SetCouncil { council: Council },
+    SetAdmin { account_id: AccountId },
+    SetGuardian { guardian: Option<AccountId> },
+    Pause { methods: Vec<String> },
+    Unpause { methods: Vec<String> },
+    // Forward a settings call to the proposal contract, see `configure_proposal_contract`
+    ConfigureProposalContract { method_name: String, args: Base64VecU8 },
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingAction {
+    pub id: u64,
+    pub action: CouncilAction,
+    pub proposer: AccountId,
+    pub approvals: Vec<AccountId>,
+    pub expires_at: u64,
+}
+
+#[near_bindgen]
+impl DAO {
+    // Hand the admin-only operations over to a council, only the admin can do it and only once.
+    // Later changes go through a `SetCouncil` action.
+    pub fn set_council(&mut self, council: Council) {
+        self.assert_not_paused("set_council");
+        assert!(self.council.is_none(), "Council is already set, propose a SetCouncil action instead");
+        assert!(self.admin == Some(env::predecessor_account_id()), "Only the admin can set the council");
+        council.assert_valid();
+        env::log_str(&format!("Council set to {:?}", council));
+        self.council = Some(council);
+    }
+
+    pub fn get_council(&self) -> Option<Council> {
+        self.council.clone()
+    }
+
+    // Open a pending action, the proposer's approval is counted right away
+    pub fn propose_action(&mut self, action: CouncilAction) -> u64 {
+        let council = self.council.as_ref().expect("Council is not set");
+        let proposer = env::predecessor_account_id();
+        assert!(council.members.contains(&proposer), "Only council members can propose actions");
+        let action_expiry = council.action_expiry;
+        self.prune_expired_actions(PRUNE_BATCH);
+        let action_id = self.next_action_id;
+        self.next_action_id += 1;
+        let pending_action = PendingAction {
+            id: action_id,
+            action,
+            proposer: proposer.clone(),
+            approvals: vec![],
+            expires_at: env::block_timestamp().saturating_add(action_expiry),
+        };
+        self.pending_actions.insert(&action_id, &pending_action);
+        env::log_str(&format!("Council action {} proposed by {}: {:?}", action_id, proposer, pending_action.action));
+        self.approve_action(action_id);
+        action_id
+    }
+
+    // Approve a pending action, it runs as soon as it reaches the threshold. Returns whether it ran.
+    pub fn approve_action(&mut self, action_id: u64) -> bool {
+        let council = self.council.clone().expect("Council is not set");
+        let member = env::predecessor_account_id();
+        assert!(council.members.contains(&member), "Only council members can approve actions");
+        let mut pending_action = self.pending_actions.get(&action_id).expect("Action not found");
+        assert!(env::block_timestamp() <= pending_action.expires_at, "Action has expired");
+        assert!(!pending_action.approvals.contains(&member), "Action already approved by {}", member);
+        pending_action.approvals.push(member.clone());
+        env::log_str(&format!("Council action {} approved by {}", action_id, member));
+
+        // Members removed from the council since approving no longer count
+        let approvals = pending_action.approvals.iter().filter(|account| council.members.contains(account)).count();
+        if approvals >= council.threshold as usize {
+            self.pending_actions.remove(&action_id);
+            self.execute_action(pending_action.action);
+            env::log_str(&format!("Council action {} executed", action_id));
+            true
+        } else {
+            self.pending_actions.insert(&action_id, &pending_action);
+            false
+        }
+    }
+
+    // Withdraw an approval, the action is dropped once nobody approves it
+    pub fn revoke_approval(&mut self, action_id: u64) {
+        let member = env::predecessor_account_id();
+        let mut pending_action = self.pending_actions.get(&action_id).expect("Action not found");
+        let position = pending_action
+            .approvals
+            .iter()
+            .position(|account| account == &member)
+            .expect("Action is not approved by the caller");
+        pending_action.approvals.remove(position);
+        env::log_str(&format!("Council action {} approval revoked by {}", action_id, member));
+        if pending_action.approvals.is_empty() {
+            self.pending_actions.remove(&action_id);
+        } else {
+            self.pending_actions.insert(&action_id, &pending_action);
+        }
+    }
+
+    pub fn get_pending_action(&self, action_id: u64) -> Option<PendingAction> {
+        self.pending_actions.get(&action_id)
+    }
+
+    // Drop up to `limit` expired actions, anyone can call it. Returns how many were dropped.
+    pub fn purge_expired_actions(&mut self, limit: Option<u64>) -> u64 {
+        self.prune_expired_actions(limit.unwrap_or(DEFAULT_PAGE_SIZE))
+    }
+
+    // List pending actions, expired ones included until they are pruned
+    pub fn get_pending_actions(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<PendingAction> {
+        self.pending_actions
+            .values()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_SIZE) as usize)
+            .collect()
+    }
+}
+
+impl DAO {
+    fn execute_action(&mut self, action: CouncilAction) {
+        match action {
+            CouncilAction::AllowProposal { proposer } => self.internal_allow_proposal(proposer),
+            CouncilAction::FinalizeProposal { proposal_id } => {
+                self.assert_not_paused("finalize_proposal");
+                // The promise is detached, the callback still records the final state
+                self.internal_finalize_proposal(proposal_id);
+            }
+            CouncilAction::SetGasConfig { gas_config } => self.internal_set_gas_config(gas_config),
+            CouncilAction::SetCouncil { council } => {
+                council.assert_valid();
+                env::log_str(&format!("Council set to {:?}", council));
+                self.council = Some(council);
+            }
+            CouncilAction::SetAdmin { account_id } => self.internal_set_admin(Some(account_id)),
+            CouncilAction::SetGuardian { guardian } => self.internal_set_guardian(guardian),
+            CouncilAction::Pause { methods } => self.internal_pause(methods),
+            CouncilAction::Unpause { methods } => self.internal_unpause(methods),
+            CouncilAction::ConfigureProposalContract { method_name, args } => {
+                // The promise is detached, a failed call only shows in the receipt outcome
+                self.internal_configure_proposal_contract(method_name, args);
+            }
+        }
+    }
+
+    fn prune_expired_actions(&mut self, limit: u64) -> u64 {
+        let now = env::block_timestamp();
+        let expired: Vec<u64> = self
+            .pending_actions
+            .values()
+            .filter(|pending_action| pending_action.expires_at < now)
+            .map(|pending_action| pending_action.id)
+            .take(limit as usize)
+            .collect();
+        for action_id in &expired {
+            self.pending_actions.remove(action_id);
+            env::log_str(&format!("Council action {} expired", action_id));
+        }
+        expired.len() as u64
+    }
+
+    // Admin-only operations are closed to the admin once a council is set
+    pub(crate) fn assert_admin(&self, message: &str) {
+        assert!(self.council.is_none(), "Council approval required");
+        assert!(self.admin == Some(env::predecessor_account_id()), "{}", message);
+    }
+}
+
+// The rest of this file holds the inline tests for the code above
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{testing_env, Gas};
+
+    const EXPIRY: u64 = 1_000;
+
+    fn set_caller(account: &str, block_timestamp: u64) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("dao".parse().unwrap())
+            .predecessor_account_id(account.parse().unwrap())
+            .block_timestamp(block_timestamp)
+            .build());
+    }
+
+    fn setup_dao() -> DAO {
+        set_caller("admin", 0);
+        let mut contract = DAO::new("admin".parse().unwrap(), "proposal".parse().unwrap());
+        contract.set_council(Council {
+            members: vec!["alice".parse().unwrap(), "bob".parse().unwrap(), "carol".parse().unwrap()],
+            threshold: 2,
+            action_expiry: EXPIRY,
+        });
+        contract
+    }
+
+    fn gas_action() -> CouncilAction {
+        CouncilAction::SetGasConfig {
+            gas_config: GasConfig { finalize_proposal: Gas::from_tgas(40), ..Default::default() },
+        }
+    }
+
+    #[test]
+    fn test_action_runs_at_threshold() {
+        let mut contract = setup_dao();
+        set_caller("alice", 0);
+        let action_id = contract.propose_action(gas_action());
+        assert_eq!(contract.get_pending_actions(None, None).len(), 1);
+        assert_eq!(contract.get_gas_config().finalize_proposal, Gas::from_tgas(10));
+
+        set_caller("bob", 10);
+        assert!(contract.approve_action(action_id));
+        assert_eq!(contract.get_gas_config().finalize_proposal, Gas::from_tgas(40));
+        assert!(contract.get_pending_action(action_id).is_none());
+    }
+
+    #[test]
+    fn test_revoke_approval_drops_empty_action() {
+        let mut contract = setup_dao();
+        set_caller("alice", 0);
+        let action_id = contract.propose_action(gas_action());
+        contract.revoke_approval(action_id);
+        assert!(contract.get_pending_action(action_id).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Action has expired")]
+    fn test_approve_expired_action() {
+        let mut contract = setup_dao();
+        set_caller("alice", 0);
+        let action_id = contract.propose_action(gas_action());
+        set_caller("bob", EXPIRY + 1);
+        contract.approve_action(action_id);
+    }
+
+    #[test]
+    fn test_expired_actions_are_pruned() {
+        let mut contract = setup_dao();
+        set_caller("alice", 0);
+        let expired_id = contract.propose_action(gas_action());
+        set_caller("bob", EXPIRY + 1);
+        let action_id = contract.propose_action(gas_action());
+        assert!(contract.get_pending_action(expired_id).is_none());
+        assert_eq!(contract.get_pending_actions(None, None).len(), 1);
+
+        set_caller("mallory", 2 * EXPIRY + 2);
+        assert_eq!(contract.purge_expired_actions(None), 1);
+        assert!(contract.get_pending_action(action_id).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Action already approved by alice")]
+    fn test_approve_twice() {
+        let mut contract = setup_dao();
+        set_caller("alice", 0);
+        let action_id = contract.propose_action(gas_action());
+        contract.approve_action(action_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only council members can propose actions")]
+    fn test_propose_not_member() {
+        let mut contract = setup_dao();
+        set_caller("mallory", 0);
+        contract.propose_action(gas_action());
+    }
+
+    #[test]
+    #[should_panic(expected = "Council approval required")]
+    fn test_admin_locked_out_in_council_mode() {
+        let mut contract = setup_dao();
+        set_caller("admin", 0);
+        contract.finalize_proposal(0);
+    }
+
+    #[test]
+    fn test_council_allows_one_proposal() {
+        let mut contract = setup_dao();
+        set_caller("alice", 0);
+        let action_id = contract.propose_action(CouncilAction::AllowProposal { proposer: "dave".parse().unwrap() });
+        set_caller("bob", 0);
+        contract.approve_action(action_id);
+
+        set_caller("dave", 0);
+        let _ = contract.create_proposal("title".to_string(), "description".to_string(), 1000, vec!["Yes".to_string(), "No".to_string()], 1, None, None, None, None, None, None);
+        assert_eq!(contract.proposal_allowances.get(&"dave".parse().unwrap()), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "No council approved proposal left for admin")]
+    fn test_admin_cannot_create_proposal_in_council_mode() {
+        let mut contract = setup_dao();
+        set_caller("admin", 0);
+        let _ = contract.create_proposal("title".to_string(), "description".to_string(), 1000, vec!["Yes".to_string(), "No".to_string()], 1, None, None, None, None, None, None);
+    }
+
+    #[test]
+    fn test_council_changes_admin() {
+        let mut contract = setup_dao();
+        set_caller("alice", 0);
+        let action_id = contract.propose_action(CouncilAction::SetAdmin { account_id: "new-admin".parse().unwrap() });
+        set_caller("carol", 0);
+        contract.approve_action(action_id);
+        assert_eq!(contract.get_admin_id(), Some("new-admin".parse().unwrap()));
+    }
+}
@@ -0,0 +1,13 @@
+use near_sdk::env;
+use near_sdk::serde_json::{json, Value};
+
+// Log a NEP-297 event so indexers can follow governance changes
+pub(crate) fn emit_event(event: &str, data: Value) {
+    let event = json!({
+        "standard": "dao",
+        "version": "1.0.0",
+        "event": event,
+        "data": [data],
+    });
+    env::log_str(&format!("EVENT_JSON:{}", event));
+}
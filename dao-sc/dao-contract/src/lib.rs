@@ -1,8 +1,15 @@
+// The cross-contract wrappers near_bindgen generates for `create_proposal` take every argument
+#![allow(clippy::too_many_arguments)]
+
 use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{LookupMap, UnorderedMap};
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk:: {
     env, 
     near_bindgen,
     AccountId,
+    BorshStorageKey,
     PanicOnDefault,
     Gas,
     ext_contract, 
@@ -11,19 +18,117 @@ use near_sdk:: {
     PromiseError
 };
 
+mod admin;
+mod council;
+mod events;
+mod members;
+mod pause;
+mod settings;
+
+pub use council::{Council, CouncilAction, PendingAction};
+pub use members::{Member, MembershipChange, PendingMembershipChange};
+
+#[derive(BorshSerialize, BorshStorageKey)]
+#[borsh(crate = "near_sdk::borsh")]
+enum StorageKey {
+    FinalizedProposals,
+    PendingActions,
+    Members,
+    ProposalAllowances,
+    MembershipChanges,
+}
+
+// Mirror of the proposal contract's ProposalState
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ProposalState {
+    Open,
+    Closed,
+    Passed,
+    Rejected,
+}
+
+// Mirror of the proposal contract's VotingMode
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum VotingMode {
+    Open,
+    CommitReveal { commit_deadline: u64 },
+    Optimistic,
+}
+
+// Mirror of the proposal contract's VotingWeight
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum VotingWeight {
+    OnePerVoter,
+    TokenWeighted { sources: Vec<WeightedSource> },
+    Reputation { balance: Option<WeightedSource> },
+}
+
+// Mirror of the proposal contract's WeightedSource
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct WeightedSource {
+    pub token_contract_id: AccountId,
+    pub decimals: u8,
+    pub multiplier: u32,
+}
+
+// Static gas reserved for each cross-contract call and its callback,
+// prepaid gas left over is forwarded to the proposal contract call
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct GasConfig {
+    pub create_proposal: Gas,
+    pub create_proposal_callback: Gas,
+    pub finalize_proposal: Gas,
+    pub finalize_proposal_callback: Gas,
+}
+
+impl Default for GasConfig {
+    fn default() -> Self {
+        Self {
+            create_proposal: Gas::from_tgas(20),
+            create_proposal_callback: Gas::from_tgas(10),
+            finalize_proposal: Gas::from_tgas(10),
+            finalize_proposal_callback: Gas::from_tgas(10),
+        }
+    }
+}
+
 //DAO Structure
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct DAO {
-    admin: AccountId,
+    // None once the admin role is renounced in favor of the council
+    admin: Option<AccountId>,
+    // Nominee of a pending admin transfer
+    pending_admin: Option<AccountId>,
     proposal_contract_id: AccountId,
+    gas_config: GasConfig,
+    // Final state of each proposal finalized through the DAO
+    finalized_proposals: LookupMap<u64, ProposalState>,
+    // Takes over the admin-only operations once set
+    council: Option<Council>,
+    pending_actions: UnorderedMap<u64, PendingAction>,
+    next_action_id: u64,
+    // Can pause methods in an emergency, only governance unpauses them
+    guardian: Option<AccountId>,
+    paused_methods: Vec<String>,
+    members: UnorderedMap<AccountId, Member>,
+    // Proposals each account may still create, granted by `AllowProposal` council actions
+    proposal_allowances: LookupMap<AccountId, u32>,
+    // Membership changes waiting on the approval of the members
+    membership_changes: LookupMap<u64, PendingMembershipChange>,
+    next_membership_change_id: u64,
 }
 
 // Cross-Contract Call Needed
 #[ext_contract(proposal_contract)]
 pub trait ProposalContractInterface {
-    fn create_proposal(&mut self, title: String, description: String, deadline: u64, options_vec: Vec<String>, minimum_votes: u8);
-    fn update_status(&mut self, proposal_id: u64);
+    fn create_proposal(&mut self, title: String, description: String, deadline: u64, options_vec: Vec<String>, minimum_votes: u8, link: Option<String>, content_hash: Option<Base64VecU8>, category: Option<String>, tags: Option<Vec<String>>, voting_mode: Option<VotingMode>, voting_weight: Option<VotingWeight>);
+    fn update_status(&mut self, proposal_id: u64) -> ProposalState;
 }
 
 // Implement the DAO structure
@@ -34,64 +139,172 @@ impl DAO {
         //assert!(!env::state_exists(), "Already initialized");
         env::log_str("Starting initialization.");
         Self {
-            admin: admin_account_id,
+            admin: Some(admin_account_id),
+            pending_admin: None,
             proposal_contract_id,
+            gas_config: GasConfig::default(),
+            finalized_proposals: LookupMap::new(StorageKey::FinalizedProposals),
+            council: None,
+            pending_actions: UnorderedMap::new(StorageKey::PendingActions),
+            next_action_id: 0,
+            guardian: None,
+            paused_methods: Vec::new(),
+            members: UnorderedMap::new(StorageKey::Members),
+            proposal_allowances: LookupMap::new(StorageKey::ProposalAllowances),
+            membership_changes: LookupMap::new(StorageKey::MembershipChanges),
+            next_membership_change_id: 0,
         }
     }
 
-    pub fn get_admin_id(&self) -> &AccountId {
+    pub fn get_admin_id(&self) -> Option<AccountId> {
         env::log_str(&format!("Current block timestamp: {}", env::block_timestamp()));
-        &self.admin
+        self.admin.clone()
     }
 
     pub fn get_proposal_contract_id(&self) -> &AccountId {
         &self.proposal_contract_id
     }
 
-    pub fn create_proposal(&mut self, title: String, description: String, deadline: u64, options_vec: Vec<String>, minimum_votes: u8) -> Promise {
-        // Verify the caller is the admin
-        assert_eq!(env::predecessor_account_id(), self.admin, "Only the admin can create proposals");
+    pub fn get_gas_config(&self) -> &GasConfig {
+        &self.gas_config
+    }
+
+    pub fn set_gas_config(&mut self, gas_config: GasConfig) {
+        self.assert_admin("Only the admin can update the gas config");
+        self.internal_set_gas_config(gas_config);
+    }
+
+    // Get the final state recorded when a proposal was finalized through the DAO
+    pub fn get_finalized_state(&self, proposal_id: u64) -> Option<ProposalState> {
+        self.finalized_proposals.get(&proposal_id)
+    }
+
+    // The attached deposit is forwarded to pay for the proposal storage, the excess is refunded to the signer.
+    // Only the admin creates proposals until a council is set, then each one needs an approved `AllowProposal` action.
+    #[payable]
+    pub fn create_proposal(&mut self, title: String, description: String, deadline: u64, options_vec: Vec<String>, minimum_votes: u8, link: Option<String>, content_hash: Option<Base64VecU8>, category: Option<String>, tags: Option<Vec<String>>, voting_mode: Option<VotingMode>, voting_weight: Option<VotingWeight>) -> Promise {
+        self.assert_not_paused("create_proposal");
+        let caller = env::predecessor_account_id();
+        let deposit = env::attached_deposit();
+        let allowance_used = self.assert_can_propose(&caller);
         env::log_str(&format!("Calling create_proposal on: {}", self.proposal_contract_id));
         env::log_str(&format!("With data: title={}, deadline={}", title, deadline));
 
         let promise = proposal_contract::ext(self.proposal_contract_id.clone())
-            .with_attached_deposit(NearToken::from_near(0))
-            .with_static_gas(Gas::from_tgas(20))
+            .with_attached_deposit(deposit)
+            .with_static_gas(self.gas_config.create_proposal)
+            .with_unused_gas_weight(1)
             .create_proposal(
                 title,
                 description,
                 deadline,
                 options_vec,
                 minimum_votes,
+                link,
+                content_hash,
+                category,
+                tags,
+                voting_mode,
+                voting_weight,
             );
         promise.then(
             Self::ext(env::current_account_id())
-                .with_static_gas(Gas::from_tgas(10))
-                .create_proposal_callback()     
+                .with_static_gas(self.gas_config.create_proposal_callback)
+                .with_unused_gas_weight(0)
+                .create_proposal_callback(caller, deposit, allowance_used)
         )
     }
 
+    // A failed creation sends its deposit back to the DAO, which passes it on to the caller
+    // together with the allowance the attempt used
     #[private]
-    pub fn create_proposal_callback (&mut self, #[callback_result] call_result: Result<u64, PromiseError>) -> u64 {
+    pub fn create_proposal_callback (&mut self, caller: AccountId, deposit: NearToken, allowance_used: bool, #[callback_result] call_result: Result<u64, PromiseError>) -> Option<u64> {
         match call_result {
             Ok(proposal_id) => {
                 env::log_str(&format!("Proposal created with ID: {}", proposal_id));
-                proposal_id
+                Some(proposal_id)
             },
             Err(e) => {
-                env::panic_str(&format!("Failed to create proposal: {:?}", e));
+                env::log_str(&format!("Failed to create proposal: {:?}", e));
+                if !deposit.is_zero() {
+                    Promise::new(caller.clone()).transfer(deposit);
+                }
+                if allowance_used {
+                    self.internal_allow_proposal(caller);
+                }
+                None
             }
         }
     }
 
-    pub fn finalize_proposal(&mut self, proposal_id: u64) {
+    pub fn finalize_proposal(&mut self, proposal_id: u64) -> Promise {
         // Verify the caller is the admin
-        assert_eq!(env::predecessor_account_id(), self.admin, "Only the admin can finalize proposals");
+        self.assert_admin("Only the admin can finalize proposals");
+        self.assert_not_paused("finalize_proposal");
+        self.internal_finalize_proposal(proposal_id)
+    }
+
+    #[private]
+    pub fn finalize_proposal_callback(&mut self, proposal_id: u64, #[callback_result] call_result: Result<ProposalState, PromiseError>) -> ProposalState {
+        match call_result {
+            Ok(state) => {
+                env::log_str(&format!("Proposal {} finalized as {:?}", proposal_id, state));
+                self.finalized_proposals.insert(&proposal_id, &state);
+                state
+            },
+            Err(e) => {
+                env::panic_str(&format!("Failed to finalize proposal {}: {:?}", proposal_id, e));
+            }
+        }
+    }
+}
 
-        proposal_contract::ext(self.proposal_contract_id.clone())
+impl DAO {
+    pub(crate) fn internal_finalize_proposal(&self, proposal_id: u64) -> Promise {
+        let promise = proposal_contract::ext(self.proposal_contract_id.clone())
             .with_attached_deposit(NearToken::from_near(0))
-            .with_static_gas(Gas::from_tgas(5))
+            .with_static_gas(self.gas_config.finalize_proposal)
+            .with_unused_gas_weight(1)
             .update_status(proposal_id);
+        promise.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(self.gas_config.finalize_proposal_callback)
+                .with_unused_gas_weight(0)
+                .finalize_proposal_callback(proposal_id)
+        )
+    }
+
+    // Only the admin proposes until a council is set, then each proposal uses up an allowance.
+    // Returns whether an allowance was used, so a failed creation can give it back.
+    pub(crate) fn assert_can_propose(&mut self, caller: &AccountId) -> bool {
+        if self.council.is_some() {
+            self.use_proposal_allowance(caller);
+            true
+        } else {
+            self.assert_admin("Only the admin can create proposals");
+            false
+        }
+    }
+
+    fn use_proposal_allowance(&mut self, account_id: &AccountId) {
+        let allowance = self.proposal_allowances.get(account_id).unwrap_or(0);
+        assert!(allowance > 0, "No council approved proposal left for {}", account_id);
+        if allowance == 1 {
+            self.proposal_allowances.remove(account_id);
+        } else {
+            self.proposal_allowances.insert(account_id, &(allowance - 1));
+        }
+    }
+
+    pub(crate) fn internal_allow_proposal(&mut self, account_id: AccountId) {
+        let allowance = self.proposal_allowances.get(&account_id).unwrap_or(0);
+        self.proposal_allowances.insert(&account_id, &(allowance + 1));
+        env::log_str(&format!("{} may create {} more proposals", account_id, allowance + 1));
+    }
+
+    pub(crate) fn internal_set_gas_config(&mut self, gas_config: GasConfig) {
+        env::log_str(&format!("Gas config updated to {:?}", gas_config));
+        self.gas_config = gas_config;
     }
 }
 
@@ -101,6 +314,8 @@ impl DAO {
 mod tests {
     use super::*;
     use near_sdk::{testing_env, VMContext};
+    use near_sdk::mock::MockAction;
+    use near_sdk::test_utils::get_created_receipts;
     use near_sdk::PublicKey;
     use std::str::FromStr;
 
@@ -116,6 +331,12 @@ mod tests {
             1000,
             vec!["option1".to_string(), "option2".to_string()],
             2,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
     }
 
@@ -161,6 +382,12 @@ mod tests {
             1000,
             vec!["option1".to_string(), "option2".to_string()],
             2,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
     }
 
@@ -173,12 +400,96 @@ mod tests {
         contract.finalize_proposal(0);
     }
 
+    #[test]
+    fn test_finalize_proposal_callback() {
+        let context = get_context("dao".parse().unwrap());
+        testing_env!(context);
+        let mut contract = DAO::new("admin".parse().unwrap(), "proposal".parse().unwrap());
+        let state = contract.finalize_proposal_callback(0, Ok(ProposalState::Passed));
+        assert_eq!(state, ProposalState::Passed);
+        assert_eq!(contract.get_finalized_state(0), Some(ProposalState::Passed));
+        assert_eq!(contract.get_finalized_state(1), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to finalize proposal 0")]
+    fn test_finalize_proposal_callback_failed() {
+        let context = get_context("dao".parse().unwrap());
+        testing_env!(context);
+        let mut contract = DAO::new("admin".parse().unwrap(), "proposal".parse().unwrap());
+        contract.finalize_proposal_callback(0, Err(PromiseError::Failed));
+    }
+
+    #[test]
+    fn test_create_proposal_callback_failed_refunds_caller() {
+        let mut context = get_context("dao".parse().unwrap());
+        context.account_balance = NearToken::from_near(10);
+        testing_env!(context);
+        let mut contract = DAO::new("admin".parse().unwrap(), "proposal".parse().unwrap());
+        let result = contract.create_proposal_callback("admin".parse().unwrap(), NearToken::from_near(1), false, Err(PromiseError::Failed));
+        assert_eq!(result, None);
+        let refunds: Vec<(String, NearToken)> = get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| {
+                let receiver_id = receipt.receiver_id.to_string();
+                receipt.actions.into_iter().filter_map(move |action| match action {
+                    MockAction::Transfer { deposit, .. } => Some((receiver_id.clone(), deposit)),
+                    _ => None,
+                })
+            })
+            .collect();
+        assert_eq!(refunds, vec![("admin".to_string(), NearToken::from_near(1))]);
+    }
+
+    #[test]
+    fn test_set_gas_config() {
+        let context = get_context("admin".parse().unwrap());
+        testing_env!(context);
+        let mut contract = DAO::new("admin".parse().unwrap(), "proposal".parse().unwrap());
+        let gas_config = GasConfig { finalize_proposal: Gas::from_tgas(30), ..Default::default() };
+        contract.set_gas_config(gas_config.clone());
+        assert_eq!(contract.get_gas_config(), &gas_config);
+    }
+
+    #[test]
+    fn test_create_proposal_uses_configured_gas() {
+        let context = get_context("admin".parse().unwrap());
+        testing_env!(context);
+        let mut contract = DAO::new("admin".parse().unwrap(), "proposal".parse().unwrap());
+        let gas_config = GasConfig {
+            create_proposal: Gas::from_tgas(50),
+            create_proposal_callback: Gas::from_tgas(8),
+            ..Default::default()
+        };
+        contract.set_gas_config(gas_config);
+        let _ = contract.create_proposal("title".to_string(), "description".to_string(), 1000, vec!["Yes".to_string(), "No".to_string()], 1, None, None, None, None, None, None);
+
+        let calls: Vec<(Gas, u64)> = get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                MockAction::FunctionCallWeight { prepaid_gas, gas_weight, .. } => Some((prepaid_gas, gas_weight.0)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(calls, vec![(Gas::from_tgas(50), 1), (Gas::from_tgas(8), 0)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the admin can update the gas config")]
+    fn test_set_gas_config_not_admin() {
+        let context = get_context("not_admin".parse().unwrap());
+        testing_env!(context);
+        let mut contract = DAO::new("admin".parse().unwrap(), "proposal".parse().unwrap());
+        contract.set_gas_config(GasConfig::default());
+    }
+
     #[test]
     fn test_init() {
         let context = get_context("admin".parse().unwrap());
         testing_env!(context);
         let contract = DAO::new("admin".parse().unwrap(), "proposal".parse().unwrap());
-        assert_eq!(contract.admin, "admin".to_string());
+        assert_eq!(contract.admin, Some("admin".parse().unwrap()));
         assert_eq!(contract.proposal_contract_id, "proposal".to_string());
     }
 }
\ No newline at end of file
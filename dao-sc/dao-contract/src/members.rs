@@ -0,0 +1,497 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, AccountId, NearToken, Promise};
+use crate::events::emit_event;
+use crate::{DAO, DAOExt};
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+const MAX_METADATA_LENGTH: usize = 1024;
+// Kept from the proposer's deposit to store the pending change and the member entry it adds,
+// enough for metadata at its maximum length
+pub const MEMBERSHIP_STORAGE_DEPOSIT: NearToken = NearToken::from_millinear(30);
+
+// Entry of the member registry, used by working groups that vote by membership instead of tokens
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Member {
+    pub account_id: AccountId,
+    // Free-form description of the member, such as a role or a profile link
+    pub metadata: Option<String>,
+    pub joined_at: u64,
+    // Membership lapses after this timestamp, in nanoseconds
+    pub expires_at: Option<u64>,
+}
+
+impl Member {
+    pub fn is_active(&self, now: u64) -> bool {
+        !matches!(self.expires_at, Some(expires_at) if now > expires_at)
+    }
+}
+
+// Change to the member registry, applied once a majority of the active members approve it
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum MembershipChange {
+    // Adds a member or renews an existing one
+    Add { account_id: AccountId, metadata: Option<String>, expires_at: Option<u64> },
+    Remove { account_id: AccountId },
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingMembershipChange {
+    pub id: u64,
+    pub change: MembershipChange,
+    // Gets the storage deposit back unless the change adds a member
+    pub proposer: AccountId,
+    pub approvals: Vec<AccountId>,
+    // Approvals are accepted until this timestamp, in nanoseconds
+    pub deadline: u64,
+}
+
+#[near_bindgen]
+impl DAO {
+    // Put a membership change to a vote of the members, the proposer's approval is counted right away.
+    // While the registry has no active member, only the admin can propose and the change applies at once.
+    // `MEMBERSHIP_STORAGE_DEPOSIT` is kept from the attached deposit, the rest is refunded.
+    #[payable]
+    pub fn propose_membership_change(&mut self, change: MembershipChange, deadline: u64) -> u64 {
+        self.assert_not_paused("propose_membership_change");
+        let proposer = env::predecessor_account_id();
+        let deposit = env::attached_deposit();
+        assert!(
+            deposit >= MEMBERSHIP_STORAGE_DEPOSIT,
+            "Attach at least {} to cover the membership change",
+            MEMBERSHIP_STORAGE_DEPOSIT
+        );
+        assert!(deadline > env::block_timestamp(), "Deadline must be in the future");
+        match &change {
+            MembershipChange::Add { metadata, expires_at, .. } => {
+                assert_valid_metadata(metadata.as_deref());
+                assert!(
+                    !matches!(expires_at, Some(expires_at) if *expires_at <= deadline),
+                    "Membership expiry must be after the proposal deadline"
+                );
+            }
+            MembershipChange::Remove { account_id } => {
+                assert!(self.members.get(account_id).is_some(), "Not a member");
+            }
+        }
+        let bootstrap = self.active_member_count() == 0;
+        if bootstrap {
+            assert!(self.admin.as_ref() == Some(&proposer), "Only the admin can propose the first member");
+        } else {
+            assert!(self.is_active_member(&proposer), "Only members can propose membership changes");
+        }
+        let refund = deposit.saturating_sub(MEMBERSHIP_STORAGE_DEPOSIT);
+        if !refund.is_zero() {
+            Promise::new(proposer.clone()).transfer(refund);
+        }
+
+        let change_id = self.next_membership_change_id;
+        self.next_membership_change_id += 1;
+        let pending_change = PendingMembershipChange { id: change_id, change, proposer: proposer.clone(), approvals: vec![], deadline };
+        env::log_str(&format!("Membership change {} proposed by {}: {:?}", change_id, proposer, pending_change.change));
+        if bootstrap {
+            self.apply_membership_change(pending_change);
+        } else {
+            self.membership_changes.insert(&change_id, &pending_change);
+            self.approve_membership_change(change_id);
+        }
+        change_id
+    }
+
+    // Approve a pending change, it applies as soon as more than half of the active members approve it.
+    // Returns whether it was applied.
+    pub fn approve_membership_change(&mut self, change_id: u64) -> bool {
+        let member = env::predecessor_account_id();
+        assert!(self.is_active_member(&member), "Only members can approve membership changes");
+        let mut pending_change = self.membership_changes.get(&change_id).expect("Membership change not found");
+        assert!(env::block_timestamp() <= pending_change.deadline, "Membership change has expired");
+        assert!(!pending_change.approvals.contains(&member), "Membership change already approved by {}", member);
+        pending_change.approvals.push(member.clone());
+        env::log_str(&format!("Membership change {} approved by {}", change_id, member));
+
+        // Members removed or expired since approving no longer count
+        let approvals = pending_change.approvals.iter().filter(|account| self.is_active_member(account)).count() as u64;
+        if approvals * 2 > self.active_member_count() {
+            self.membership_changes.remove(&change_id);
+            self.apply_membership_change(pending_change);
+            true
+        } else {
+            self.membership_changes.insert(&change_id, &pending_change);
+            false
+        }
+    }
+
+    // Drop a change that missed its deadline and refund its storage deposit, anyone can call it
+    pub fn expire_membership_change(&mut self, change_id: u64) {
+        let pending_change = self.membership_changes.get(&change_id).expect("Membership change not found");
+        assert!(env::block_timestamp() > pending_change.deadline, "Membership change is still open");
+        self.membership_changes.remove(&change_id);
+        env::log_str(&format!("Membership change {} expired", change_id));
+        Promise::new(pending_change.proposer).transfer(MEMBERSHIP_STORAGE_DEPOSIT);
+    }
+
+    pub fn get_membership_change(&self, change_id: u64) -> Option<PendingMembershipChange> {
+        self.membership_changes.get(&change_id)
+    }
+
+    // Members keep their own metadata up to date and pay for any storage it adds,
+    // the unused part of the deposit is refunded
+    #[payable]
+    pub fn update_member_metadata(&mut self, metadata: Option<String>) {
+        self.assert_not_paused("update_member_metadata");
+        let account_id = env::predecessor_account_id();
+        let mut member = self.members.get(&account_id).expect("Not a member");
+        assert_valid_metadata(metadata.as_deref());
+        let initial_storage_usage = env::storage_usage();
+        member.metadata = metadata;
+        self.members.insert(&account_id, &member);
+
+        let storage_cost = env::storage_byte_cost()
+            .saturating_mul(env::storage_usage().saturating_sub(initial_storage_usage) as u128);
+        let deposit = env::attached_deposit();
+        assert!(deposit >= storage_cost, "Attach at least {} to cover the metadata storage", storage_cost);
+        let refund = deposit.saturating_sub(storage_cost);
+        if !refund.is_zero() {
+            Promise::new(account_id).transfer(refund);
+        }
+    }
+
+    // True for registered members whose membership has not expired
+    pub fn is_member(&self, account_id: AccountId) -> bool {
+        self.is_active_member(&account_id)
+    }
+
+    // One vote per active member, queried by proposal contracts that vote by membership
+    pub fn get_member_voting_power(&self, account_id: AccountId) -> U128 {
+        U128(self.is_member(account_id) as u128)
+    }
+
+    pub fn get_member(&self, account_id: AccountId) -> Option<Member> {
+        self.members.get(&account_id)
+    }
+
+    // Expired members are listed too, check `expires_at`
+    pub fn get_members(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<Member> {
+        self.members
+            .values()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_SIZE) as usize)
+            .collect()
+    }
+
+    pub fn get_member_count(&self) -> u64 {
+        self.members.len()
+    }
+}
+
+impl DAO {
+    pub(crate) fn internal_add_member(&mut self, account_id: AccountId, metadata: Option<String>, expires_at: Option<u64>) {
+        assert_valid_metadata(metadata.as_deref());
+        let now = env::block_timestamp();
+        assert!(!matches!(expires_at, Some(expires_at) if expires_at <= now), "Membership expiry must be in the future");
+        let joined_at = self.members.get(&account_id).map_or(now, |member| member.joined_at);
+        let member = Member { account_id: account_id.clone(), metadata, joined_at, expires_at };
+        self.members.insert(&account_id, &member);
+        emit_event("member_added", json!({ "account_id": account_id, "expires_at": expires_at }));
+    }
+
+    pub(crate) fn internal_remove_member(&mut self, account_id: AccountId) {
+        self.members.remove(&account_id).expect("Not a member");
+        emit_event("member_removed", json!({ "account_id": account_id }));
+    }
+
+    fn is_active_member(&self, account_id: &AccountId) -> bool {
+        self.members
+            .get(account_id)
+            .is_some_and(|member| member.is_active(env::block_timestamp()))
+    }
+
+    // Walks the whole registry, which stays small for the working groups it is meant for
+    fn active_member_count(&self) -> u64 {
+        let now = env::block_timestamp();
+        self.members.values().filter(|member| member.is_active(now)).count() as u64
+    }
+
+    // An added member keeps the storage deposit, otherwise it goes back to the proposer. Changes that
+    // no longer apply, such as an expiry that has passed in the meantime, are dropped instead of failing.
+    fn apply_membership_change(&mut self, pending_change: PendingMembershipChange) {
+        let PendingMembershipChange { id, change, proposer, .. } = pending_change;
+        let now = env::block_timestamp();
+        let keeps_deposit = match change {
+            MembershipChange::Add { account_id, metadata, expires_at }
+                if !matches!(expires_at, Some(expires_at) if expires_at <= now) =>
+            {
+                self.internal_add_member(account_id, metadata, expires_at);
+                true
+            }
+            MembershipChange::Remove { account_id } if self.members.get(&account_id).is_some() => {
+                self.internal_remove_member(account_id);
+                false
+            }
+            change => {
+                env::log_str(&format!("Membership change {} no longer applies: {:?}", id, change));
+                false
+            }
+        };
+        if !keeps_deposit {
+            Promise::new(proposer).transfer(MEMBERSHIP_STORAGE_DEPOSIT);
+        }
+    }
+}
+
+fn assert_valid_metadata(metadata: Option<&str>) {
+    if let Some(metadata) = metadata {
+        assert!(metadata.len() <= MAX_METADATA_LENGTH, "Member metadata exceeds {} bytes", MAX_METADATA_LENGTH);
+    }
+}
+
+// The rest of this file holds the inline tests for the code above
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::mock::MockAction;
+    use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    fn set_caller(account: &str, block_timestamp: u64) {
+        set_context(account, block_timestamp, NearToken::from_near(0));
+    }
+
+    fn set_context(account: &str, block_timestamp: u64, deposit: NearToken) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("dao".parse().unwrap())
+            .predecessor_account_id(account.parse().unwrap())
+            .block_timestamp(block_timestamp)
+            .account_balance(NearToken::from_near(10))
+            .attached_deposit(deposit)
+            .build());
+    }
+
+    fn setup_dao() -> DAO {
+        set_caller("admin", 0);
+        DAO::new("admin".parse().unwrap(), "proposal".parse().unwrap())
+    }
+
+    fn add_alice() -> MembershipChange {
+        MembershipChange::Add { account_id: "alice".parse().unwrap(), metadata: Some("Treasurer".to_string()), expires_at: None }
+    }
+
+    fn transfers() -> Vec<(String, NearToken)> {
+        get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| {
+                let receiver_id = receipt.receiver_id.to_string();
+                receipt.actions.into_iter().filter_map(move |action| match action {
+                    MockAction::Transfer { deposit, .. } => Some((receiver_id.clone(), deposit)),
+                    _ => None,
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_membership_expires() {
+        let mut contract = setup_dao();
+        contract.internal_add_member("alice".parse().unwrap(), Some("Treasurer".to_string()), Some(100));
+        assert!(contract.is_member("alice".parse().unwrap()));
+        assert_eq!(contract.get_member_voting_power("alice".parse().unwrap()), U128(1));
+
+        set_caller("admin", 101);
+        assert!(!contract.is_member("alice".parse().unwrap()));
+        assert_eq!(contract.get_member_voting_power("alice".parse().unwrap()), U128(0));
+        assert_eq!(contract.get_member_count(), 1);
+    }
+
+    #[test]
+    fn test_renewal_keeps_join_date() {
+        let mut contract = setup_dao();
+        contract.internal_add_member("alice".parse().unwrap(), None, Some(100));
+        set_caller("admin", 50);
+        contract.internal_add_member("alice".parse().unwrap(), None, Some(500));
+        let member = contract.get_member("alice".parse().unwrap()).unwrap();
+        assert_eq!(member.joined_at, 0);
+        assert_eq!(member.expires_at, Some(500));
+    }
+
+    #[test]
+    fn test_member_updates_metadata() {
+        let mut contract = setup_dao();
+        contract.internal_add_member("alice".parse().unwrap(), None, None);
+        set_context("alice", 0, NearToken::from_millinear(10));
+        contract.update_member_metadata(Some("https://example.com/alice".to_string()));
+        assert_eq!(contract.get_members(None, None)[0].metadata, Some("https://example.com/alice".to_string()));
+        // The unused part of the deposit is refunded
+        let refunds = transfers();
+        assert_eq!(refunds.len(), 1);
+        assert!(refunds[0].1 < NearToken::from_millinear(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "to cover the metadata storage")]
+    fn test_update_metadata_without_deposit() {
+        let mut contract = setup_dao();
+        contract.internal_add_member("alice".parse().unwrap(), None, None);
+        set_caller("alice", 0);
+        contract.update_member_metadata(Some("https://example.com/alice".to_string()));
+    }
+
+    #[test]
+    fn test_admin_adds_first_member() {
+        let mut contract = setup_dao();
+        set_context("admin", 0, NearToken::from_near(1));
+        let change_id = contract.propose_membership_change(add_alice(), 1_000);
+        assert!(contract.is_member("alice".parse().unwrap()));
+        assert_eq!(contract.get_membership_change(change_id), None);
+        // The storage deposit is kept for the new member, the rest is refunded
+        assert_eq!(transfers(), vec![("admin".to_string(), NearToken::from_near(1).saturating_sub(MEMBERSHIP_STORAGE_DEPOSIT))]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the admin can propose the first member")]
+    fn test_first_member_not_admin() {
+        let mut contract = setup_dao();
+        set_context("mallory", 0, NearToken::from_near(1));
+        contract.propose_membership_change(add_alice(), 1_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only members can propose membership changes")]
+    fn test_admin_cannot_propose_once_members_exist() {
+        let mut contract = setup_dao();
+        contract.internal_add_member("bob".parse().unwrap(), None, None);
+        set_context("admin", 0, MEMBERSHIP_STORAGE_DEPOSIT);
+        contract.propose_membership_change(add_alice(), 1_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "to cover the membership change")]
+    fn test_propose_membership_change_without_deposit() {
+        let mut contract = setup_dao();
+        contract.propose_membership_change(add_alice(), 1_000);
+    }
+
+    #[test]
+    fn test_majority_of_members_adds_member() {
+        let mut contract = setup_dao();
+        for member in ["bob", "carol", "dave"] {
+            contract.internal_add_member(member.parse().unwrap(), None, None);
+        }
+        set_context("bob", 0, MEMBERSHIP_STORAGE_DEPOSIT);
+        let change_id = contract.propose_membership_change(add_alice(), 1_000);
+        assert!(!contract.is_member("alice".parse().unwrap()));
+        assert_eq!(contract.get_membership_change(change_id).unwrap().approvals, vec!["bob".parse::<AccountId>().unwrap()]);
+
+        set_caller("carol", 10);
+        assert!(contract.approve_membership_change(change_id));
+        assert!(contract.is_member("alice".parse().unwrap()));
+        assert_eq!(contract.get_membership_change(change_id), None);
+        assert_eq!(transfers(), vec![]);
+    }
+
+    #[test]
+    fn test_expired_members_do_not_count() {
+        let mut contract = setup_dao();
+        contract.internal_add_member("bob".parse().unwrap(), None, None);
+        contract.internal_add_member("carol".parse().unwrap(), None, Some(100));
+        set_context("bob", 200, MEMBERSHIP_STORAGE_DEPOSIT);
+        // Bob is the only active member left, so the proposer's approval is a majority
+        contract.propose_membership_change(add_alice(), 1_000);
+        assert!(contract.is_member("alice".parse().unwrap()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only members can approve membership changes")]
+    fn test_approve_membership_change_not_member() {
+        let mut contract = setup_dao();
+        for member in ["bob", "carol"] {
+            contract.internal_add_member(member.parse().unwrap(), None, None);
+        }
+        set_context("bob", 0, MEMBERSHIP_STORAGE_DEPOSIT);
+        let change_id = contract.propose_membership_change(add_alice(), 1_000);
+        set_caller("mallory", 0);
+        contract.approve_membership_change(change_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Membership change already approved by bob")]
+    fn test_approve_membership_change_twice() {
+        let mut contract = setup_dao();
+        for member in ["bob", "carol"] {
+            contract.internal_add_member(member.parse().unwrap(), None, None);
+        }
+        set_context("bob", 0, MEMBERSHIP_STORAGE_DEPOSIT);
+        let change_id = contract.propose_membership_change(add_alice(), 1_000);
+        contract.approve_membership_change(change_id);
+    }
+
+    #[test]
+    fn test_majority_of_members_removes_member() {
+        let mut contract = setup_dao();
+        for member in ["alice", "bob", "carol"] {
+            contract.internal_add_member(member.parse().unwrap(), None, None);
+        }
+        set_context("bob", 0, MEMBERSHIP_STORAGE_DEPOSIT);
+        let change_id = contract.propose_membership_change(MembershipChange::Remove { account_id: "alice".parse().unwrap() }, 1_000);
+        set_caller("carol", 0);
+        contract.approve_membership_change(change_id);
+        assert_eq!(contract.get_member_count(), 2);
+        assert_eq!(transfers(), vec![("bob".to_string(), MEMBERSHIP_STORAGE_DEPOSIT)]);
+    }
+
+    #[test]
+    fn test_expired_membership_change_refunds_storage_deposit() {
+        let mut contract = setup_dao();
+        for member in ["bob", "carol"] {
+            contract.internal_add_member(member.parse().unwrap(), None, None);
+        }
+        set_context("bob", 0, MEMBERSHIP_STORAGE_DEPOSIT);
+        let change_id = contract.propose_membership_change(add_alice(), 1_000);
+        set_caller("anyone", 1_001);
+        contract.expire_membership_change(change_id);
+        assert!(!contract.is_member("alice".parse().unwrap()));
+        assert_eq!(contract.get_membership_change(change_id), None);
+        assert_eq!(transfers(), vec![("bob".to_string(), MEMBERSHIP_STORAGE_DEPOSIT)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Membership change has expired")]
+    fn test_approve_expired_membership_change() {
+        let mut contract = setup_dao();
+        for member in ["bob", "carol"] {
+            contract.internal_add_member(member.parse().unwrap(), None, None);
+        }
+        set_context("bob", 0, MEMBERSHIP_STORAGE_DEPOSIT);
+        let change_id = contract.propose_membership_change(add_alice(), 1_000);
+        set_caller("carol", 1_001);
+        contract.approve_membership_change(change_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Membership change is still open")]
+    fn test_expire_open_membership_change() {
+        let mut contract = setup_dao();
+        for member in ["bob", "carol"] {
+            contract.internal_add_member(member.parse().unwrap(), None, None);
+        }
+        set_context("bob", 0, MEMBERSHIP_STORAGE_DEPOSIT);
+        let change_id = contract.propose_membership_change(add_alice(), 1_000);
+        contract.expire_membership_change(change_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Method propose_membership_change is paused")]
+    fn test_paused_propose_membership_change() {
+        let mut contract = setup_dao();
+        contract.set_guardian(Some("guardian".parse().unwrap()));
+        set_caller("guardian", 0);
+        contract.pause(vec!["propose_membership_change".to_string()]);
+        set_context("admin", 0, NearToken::from_near(1));
+        contract.propose_membership_change(add_alice(), 1_000);
+    }
+}
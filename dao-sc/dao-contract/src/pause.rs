@@ -0,0 +1,154 @@
+use near_sdk::{env, near_bindgen, AccountId};
+use crate::{DAO, DAOExt};
+
+// Methods the guardian can pause. Council actions and `unpause` stay available so governance
+// can always undo a pause.
+pub const PAUSABLE_METHODS: [&str; 5] = [
+    "create_proposal",
+    "finalize_proposal",
+    "propose_membership_change",
+    "update_member_metadata",
+    "set_council",
+];
+
+#[near_bindgen]
+impl DAO {
+    pub fn set_guardian(&mut self, guardian: Option<AccountId>) {
+        self.assert_admin("Only the admin can set the guardian");
+        self.internal_set_guardian(guardian);
+    }
+
+    pub fn get_guardian(&self) -> Option<AccountId> {
+        self.guardian.clone()
+    }
+
+    // The guardian can only pause, unpausing goes through governance
+    pub fn pause(&mut self, methods: Vec<String>) {
+        assert!(
+            self.guardian.as_ref() == Some(&env::predecessor_account_id()),
+            "Only the guardian can pause"
+        );
+        self.internal_pause(methods);
+    }
+
+    // With a council set, unpausing needs an `Unpause` council action. Until then the admin
+    // unpauses alone, so a pause only holds against a compromised admin once a council is set.
+    pub fn unpause(&mut self, methods: Vec<String>) {
+        self.assert_admin("Only the admin can unpause");
+        self.internal_unpause(methods);
+    }
+
+    pub fn get_paused_methods(&self) -> Vec<String> {
+        self.paused_methods.clone()
+    }
+}
+
+impl DAO {
+    pub(crate) fn assert_not_paused(&self, method: &str) {
+        assert!(!self.paused_methods.iter().any(|paused| paused == method), "Method {} is paused", method);
+    }
+
+    pub(crate) fn internal_set_guardian(&mut self, guardian: Option<AccountId>) {
+        env::log_str(&format!("Guardian set to {:?}", guardian));
+        self.guardian = guardian;
+    }
+
+    pub(crate) fn internal_pause(&mut self, methods: Vec<String>) {
+        for method in methods {
+            assert!(PAUSABLE_METHODS.contains(&method.as_str()), "Unknown pausable method: {}", method);
+            if !self.paused_methods.contains(&method) {
+                env::log_str(&format!("Method {} paused", method));
+                self.paused_methods.push(method);
+            }
+        }
+    }
+
+    pub(crate) fn internal_unpause(&mut self, methods: Vec<String>) {
+        for method in methods {
+            env::log_str(&format!("Method {} unpaused", method));
+            self.paused_methods.retain(|paused| paused != &method);
+        }
+    }
+}
+
+// The rest of this file holds the inline tests for the code above
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Council, CouncilAction};
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    fn set_caller(account: &str) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("dao".parse().unwrap())
+            .predecessor_account_id(account.parse().unwrap())
+            .build());
+    }
+
+    fn setup_dao() -> DAO {
+        set_caller("admin");
+        let mut contract = DAO::new("admin".parse().unwrap(), "proposal".parse().unwrap());
+        contract.set_guardian(Some("guardian".parse().unwrap()));
+        contract
+    }
+
+    #[test]
+    #[should_panic(expected = "Method finalize_proposal is paused")]
+    fn test_paused_finalize_proposal() {
+        let mut contract = setup_dao();
+        set_caller("guardian");
+        contract.pause(vec!["finalize_proposal".to_string()]);
+        set_caller("admin");
+        contract.finalize_proposal(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Method create_proposal is paused")]
+    fn test_paused_create_proposal() {
+        let mut contract = setup_dao();
+        set_caller("guardian");
+        contract.pause(vec!["create_proposal".to_string()]);
+        set_caller("admin");
+        contract.create_proposal("title".to_string(), "description".to_string(), 1000, vec!["Yes".to_string(), "No".to_string()], 1, None, None, None, None, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the admin can unpause")]
+    fn test_guardian_cannot_unpause() {
+        let mut contract = setup_dao();
+        set_caller("guardian");
+        contract.pause(vec!["finalize_proposal".to_string()]);
+        contract.unpause(vec!["finalize_proposal".to_string()]);
+    }
+
+    #[test]
+    fn test_council_unpauses() {
+        let mut contract = setup_dao();
+        contract.set_council(Council {
+            members: vec!["alice".parse().unwrap()],
+            threshold: 1,
+            action_expiry: 1_000,
+        });
+        set_caller("guardian");
+        contract.pause(vec!["finalize_proposal".to_string(), "create_proposal".to_string()]);
+        set_caller("alice");
+        contract.propose_action(CouncilAction::Unpause { methods: vec!["finalize_proposal".to_string()] });
+        assert_eq!(contract.get_paused_methods(), vec!["create_proposal".to_string()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Method set_council is paused")]
+    fn test_paused_set_council() {
+        let mut contract = setup_dao();
+        set_caller("guardian");
+        contract.pause(vec!["set_council".to_string()]);
+        set_caller("admin");
+        contract.set_council(Council {
+            members: vec!["mallory".parse().unwrap()],
+            threshold: 1,
+            action_expiry: 1_000,
+        });
+    }
+}
@@ -0,0 +1,128 @@
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::{env, near_bindgen, Gas, NearToken, Promise};
+use crate::{DAO, DAOExt};
+
+// Gas attached to each settings call forwarded to the proposal contract
+const GAS_FOR_SETTINGS_CALL: Gas = Gas::from_tgas(10);
+
+// Proposal contract methods reserved to its governance account, which is this DAO
+pub const PROPOSAL_SETTINGS_METHODS: [&str; 13] = [
+    "set_limits",
+    "set_gas_config",
+    "set_veto_config",
+    "set_voting_power_source",
+    "set_reputation_config",
+    "set_conviction_config",
+    "set_optimistic_config",
+    "add_moderator",
+    "remove_moderator",
+    "add_category",
+    "remove_category",
+    "set_guardian",
+    "unpause",
+];
+
+#[near_bindgen]
+impl DAO {
+    // Call one of the proposal contract's governed methods with JSON `args`, a council uses
+    // a `ConfigureProposalContract` action instead
+    pub fn configure_proposal_contract(&mut self, method_name: String, args: Base64VecU8) -> Promise {
+        self.assert_admin("Only the admin can configure the proposal contract");
+        self.internal_configure_proposal_contract(method_name, args)
+    }
+}
+
+impl DAO {
+    pub(crate) fn internal_configure_proposal_contract(&self, method_name: String, args: Base64VecU8) -> Promise {
+        assert!(
+            PROPOSAL_SETTINGS_METHODS.contains(&method_name.as_str()),
+            "Not a proposal contract settings method: {}",
+            method_name
+        );
+        env::log_str(&format!("Calling {} on {}", method_name, self.proposal_contract_id));
+        Promise::new(self.proposal_contract_id.clone()).function_call(
+            method_name,
+            args.0,
+            NearToken::from_near(0),
+            GAS_FOR_SETTINGS_CALL,
+        )
+    }
+}
+
+// The rest of this file holds the inline tests for the code above
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Council, CouncilAction};
+    use near_sdk::mock::MockAction;
+    use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    fn set_caller(account: &str) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("dao".parse().unwrap())
+            .predecessor_account_id(account.parse().unwrap())
+            .build());
+    }
+
+    fn forwarded_calls() -> Vec<(String, String)> {
+        get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| {
+                let receiver_id = receipt.receiver_id.to_string();
+                receipt.actions.into_iter().filter_map(move |action| match action {
+                    MockAction::FunctionCallWeight { method_name, .. } => {
+                        Some((receiver_id.clone(), String::from_utf8(method_name).unwrap()))
+                    }
+                    _ => None,
+                })
+            })
+            .collect()
+    }
+
+    fn category_args() -> Base64VecU8 {
+        br#"{"category":"grants"}"#.to_vec().into()
+    }
+
+    #[test]
+    fn test_admin_configures_proposal_contract() {
+        set_caller("admin");
+        let mut contract = DAO::new("admin".parse().unwrap(), "proposal".parse().unwrap());
+        contract.configure_proposal_contract("add_category".to_string(), category_args());
+        assert_eq!(forwarded_calls(), vec![("proposal".to_string(), "add_category".to_string())]);
+    }
+
+    #[test]
+    fn test_council_configures_proposal_contract() {
+        set_caller("admin");
+        let mut contract = DAO::new("admin".parse().unwrap(), "proposal".parse().unwrap());
+        contract.set_council(Council {
+            members: vec!["alice".parse().unwrap()],
+            threshold: 1,
+            action_expiry: 1_000,
+        });
+        set_caller("alice");
+        contract.propose_action(CouncilAction::ConfigureProposalContract {
+            method_name: "add_moderator".to_string(),
+            args: br#"{"account_id":"carol"}"#.to_vec().into(),
+        });
+        assert_eq!(forwarded_calls(), vec![("proposal".to_string(), "add_moderator".to_string())]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not a proposal contract settings method: create_proposal")]
+    fn test_configure_unknown_method() {
+        set_caller("admin");
+        let mut contract = DAO::new("admin".parse().unwrap(), "proposal".parse().unwrap());
+        contract.configure_proposal_contract("create_proposal".to_string(), category_args());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the admin can configure the proposal contract")]
+    fn test_configure_not_admin() {
+        set_caller("mallory");
+        let mut contract = DAO::new("admin".parse().unwrap(), "proposal".parse().unwrap());
+        contract.configure_proposal_contract("add_category".to_string(), category_args());
+    }
+}
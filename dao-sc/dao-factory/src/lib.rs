@@ -0,0 +1,623 @@
+use near_sdk::serde_json::json;
+use near_sdk::store::{LazyOption, LookupMap, LookupSet, Vector};
+use near_sdk::{
+    env, log, near, require, AccountId, BorshStorageKey, Gas, NearToken, PanicOnDefault, Promise,
+    PromiseResult,
+};
+
+// Gas attached to each `new` call made right after deploying a contract
+const GAS_FOR_INIT: Gas = Gas::from_tgas(10);
+// Gas reserved for `on_dao_created`
+const GAS_FOR_CREATE_CALLBACK: Gas = Gas::from_tgas(10);
+// Extra bytes paid on top of the code size to cover the account and its initial state
+const ACCOUNT_STORAGE_OVERHEAD: u128 = 10_000;
+// Default page size for `get_daos`
+const DEFAULT_PAGE_SIZE: u32 = 50;
+
+/// A DAO deployed by the factory: a `dao-contract` and its `proposal-contract`.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DaoInfo {
+    pub name: String,
+    pub dao_account_id: AccountId,
+    pub proposal_contract_id: AccountId,
+    pub token_contract_id: AccountId,
+    pub admin_account_id: AccountId,
+    pub created_at: u64,
+}
+
+/// A DAO whose deployment only partly succeeded. The deployed contract stays in place and
+/// `retry_dao` deploys the missing one.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartialDao {
+    pub info: DaoInfo,
+    // Account refunded if the deployment fails, the caller of the latest attempt
+    pub creator: AccountId,
+    pub dao_deployed: bool,
+    pub proposals_deployed: bool,
+    // Set while a deployment is in flight, so the name cannot be deployed twice at once
+    pub deploying: bool,
+}
+
+#[derive(BorshStorageKey)]
+#[near(serializers = [borsh])]
+enum StorageKey {
+    DaoCode,
+    ProposalCode,
+    Daos,
+    Names,
+    PartialDaos,
+}
+
+//Factory Structure
+#[derive(PanicOnDefault)]
+#[near(contract_state)]
+pub struct DaoFactory {
+    owner: AccountId,
+    // FDAO token used by DAOs that do not bring their own
+    default_token_contract_id: AccountId,
+    dao_code: LazyOption<Vec<u8>>,
+    proposal_code: LazyOption<Vec<u8>>,
+    daos: Vector<DaoInfo>,
+    names: LookupSet<String>,
+    // DAOs being deployed or partly deployed by name, their names stay taken until
+    // `on_dao_created` releases them or `retry_dao` completes them
+    partial_daos: LookupMap<String, PartialDao>,
+}
+
+#[near]
+impl DaoFactory {
+    #[init]
+    pub fn new(owner_id: AccountId, default_token_contract_id: AccountId) -> Self {
+        require!(!env::state_exists(), "Already initialized");
+        Self {
+            owner: owner_id,
+            default_token_contract_id,
+            dao_code: LazyOption::new(StorageKey::DaoCode, None),
+            proposal_code: LazyOption::new(StorageKey::ProposalCode, None),
+            daos: Vector::new(StorageKey::Daos),
+            names: LookupSet::new(StorageKey::Names),
+            partial_daos: LookupMap::new(StorageKey::PartialDaos),
+        }
+    }
+
+    /// Stores the `dao-contract` wasm sent as the raw call input.
+    pub fn set_dao_code(&mut self) {
+        self.assert_owner();
+        let code = env::input().expect("Expected the contract code as input");
+        log!("Stored dao-contract code ({} bytes)", code.len());
+        self.dao_code.set(Some(code));
+    }
+
+    /// Stores the `proposal-contract` wasm sent as the raw call input.
+    pub fn set_proposal_code(&mut self) {
+        self.assert_owner();
+        let code = env::input().expect("Expected the contract code as input");
+        log!("Stored proposal-contract code ({} bytes)", code.len());
+        self.proposal_code.set(Some(code));
+    }
+
+    /// Deploys a new DAO as `{name}-dao.{factory}` and its proposal contract as
+    /// `{name}-proposals.{factory}`. When `token_contract_id` is omitted the DAO votes
+    /// with the factory's default FDAO token.
+    #[payable]
+    pub fn create_dao(
+        &mut self,
+        name: String,
+        admin_account_id: AccountId,
+        token_contract_id: Option<AccountId>,
+    ) -> Promise {
+        require!(
+            !self.names.contains(&name),
+            "A DAO with this name already exists"
+        );
+        require!(
+            !self.partial_daos.contains_key(&name),
+            "A DAO with this name is being deployed or partly deployed"
+        );
+
+        let current_account_id = env::current_account_id();
+        let dao_account_id: AccountId = format!("{}-dao.{}", name, current_account_id)
+            .parse()
+            .unwrap_or_else(|_| env::panic_str("Invalid DAO name"));
+        let proposal_contract_id: AccountId = format!("{}-proposals.{}", name, current_account_id)
+            .parse()
+            .unwrap_or_else(|_| env::panic_str("Invalid DAO name"));
+        let token_contract_id =
+            token_contract_id.unwrap_or_else(|| self.default_token_contract_id.clone());
+
+        let info = DaoInfo {
+            name,
+            dao_account_id,
+            proposal_contract_id,
+            token_contract_id,
+            admin_account_id,
+            created_at: env::block_timestamp(),
+        };
+        self.deploy(PartialDao {
+            info,
+            creator: env::predecessor_account_id(),
+            dao_deployed: false,
+            proposals_deployed: false,
+            deploying: false,
+        })
+    }
+
+    /// Deploys the contract missing from a partly deployed DAO, the attached deposit pays for it.
+    #[payable]
+    pub fn retry_dao(&mut self, name: String) -> Promise {
+        let partial = self
+            .partial_daos
+            .get(&name)
+            .cloned()
+            .unwrap_or_else(|| env::panic_str("No partly deployed DAO with this name"));
+        require!(!partial.deploying, "The DAO is already being deployed");
+        self.deploy(PartialDao {
+            creator: env::predecessor_account_id(),
+            ..partial
+        })
+    }
+
+    // Records the DAO once both contracts are deployed. A failed deployment is refunded,
+    // and if the other contract made it the DAO is kept as partly deployed for `retry_dao`.
+    #[private]
+    pub fn on_dao_created(
+        &mut self,
+        partial: PartialDao,
+        dao_deposit: NearToken,
+        proposal_deposit: NearToken,
+    ) -> bool {
+        // Results come in deployment order, the DAO first, skipping contracts already deployed
+        let mut results = (0..env::promise_results_count())
+            .map(|index| matches!(env::promise_result(index), PromiseResult::Successful(_)));
+        let dao_deployed = partial.dao_deployed || results.next().unwrap_or(false);
+        let proposals_deployed = partial.proposals_deployed || results.next().unwrap_or(false);
+        let PartialDao { info, creator, .. } = partial;
+
+        if dao_deployed && proposals_deployed {
+            log!("DAO {} created at {}", info.name, info.dao_account_id);
+            self.partial_daos.remove(&info.name);
+            self.names.insert(info.name.clone());
+            self.daos.push(info);
+            return true;
+        }
+
+        let mut refund = NearToken::from_near(0);
+        if !dao_deployed {
+            refund = refund.saturating_add(dao_deposit);
+        }
+        if !proposals_deployed {
+            refund = refund.saturating_add(proposal_deposit);
+        }
+        log!(
+            "Failed to create DAO {}, refunding {} to {}",
+            info.name,
+            refund,
+            creator
+        );
+        Promise::new(creator.clone()).transfer(refund);
+
+        // Nothing was left behind when both failed, so the name is free again
+        if dao_deployed || proposals_deployed {
+            self.partial_daos.insert(
+                info.name.clone(),
+                PartialDao {
+                    info,
+                    creator,
+                    dao_deployed,
+                    proposals_deployed,
+                    deploying: false,
+                },
+            );
+        } else {
+            self.partial_daos.remove(&info.name);
+        }
+        false
+    }
+
+    // Views
+    pub fn get_owner_id(&self) -> &AccountId {
+        &self.owner
+    }
+
+    pub fn get_default_token_contract_id(&self) -> &AccountId {
+        &self.default_token_contract_id
+    }
+
+    pub fn get_partial_dao(&self, name: String) -> Option<PartialDao> {
+        self.partial_daos.get(&name).cloned()
+    }
+
+    pub fn get_dao_count(&self) -> u32 {
+        self.daos.len()
+    }
+
+    // List created DAOs, oldest first
+    pub fn get_daos(&self, from_index: Option<u32>, limit: Option<u32>) -> Vec<DaoInfo> {
+        self.daos
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_SIZE) as usize)
+            .cloned()
+            .collect()
+    }
+}
+
+impl DaoFactory {
+    fn assert_owner(&self) {
+        require!(
+            env::predecessor_account_id() == self.owner,
+            "Only the owner can update contract code"
+        );
+    }
+
+    // Deploys the contracts `partial` is missing, the DAO and its proposal contract as
+    // `{name}-dao.{factory}` and `{name}-proposals.{factory}`. The name is reserved in
+    // `partial_daos` until the callback.
+    fn deploy(&mut self, partial: PartialDao) -> Promise {
+        let info = &partial.info;
+        let dao_code = (!partial.dao_deployed).then(|| {
+            self.dao_code
+                .get()
+                .clone()
+                .unwrap_or_else(|| env::panic_str("DAO contract code not set"))
+        });
+        let proposal_code = (!partial.proposals_deployed).then(|| {
+            self.proposal_code
+                .get()
+                .clone()
+                .unwrap_or_else(|| env::panic_str("Proposal contract code not set"))
+        });
+
+        let dao_deposit = dao_code
+            .as_ref()
+            .map_or(NearToken::from_near(0), |code| Self::storage_cost(code.len()));
+        let proposal_deposit = proposal_code
+            .as_ref()
+            .map_or(NearToken::from_near(0), |code| Self::storage_cost(code.len()));
+        let required = dao_deposit.saturating_add(proposal_deposit);
+        let attached = env::attached_deposit();
+        require!(
+            attached >= required,
+            format!("Attach at least {} to deploy the DAO", required)
+        );
+
+        let excess = attached.saturating_sub(required);
+        if !excess.is_zero() {
+            Promise::new(partial.creator.clone()).transfer(excess);
+        }
+        log!(
+            "Deploying DAO {} with proposals at {}",
+            info.dao_account_id,
+            info.proposal_contract_id
+        );
+
+        let deploy_dao = dao_code.map(|code| {
+            Promise::new(info.dao_account_id.clone())
+                .create_account()
+                .transfer(dao_deposit)
+                .deploy_contract(code)
+                .function_call(
+                    "new".to_string(),
+                    json!({
+                        "admin_account_id": info.admin_account_id,
+                        "proposal_contract_id": info.proposal_contract_id,
+                    })
+                    .to_string()
+                    .into_bytes(),
+                    NearToken::from_near(0),
+                    GAS_FOR_INIT,
+                )
+        });
+        // The DAO governs the settings of its proposal contract
+        let deploy_proposals = proposal_code.map(|code| {
+            Promise::new(info.proposal_contract_id.clone())
+                .create_account()
+                .transfer(proposal_deposit)
+                .deploy_contract(code)
+                .function_call(
+                    "new".to_string(),
+                    json!({
+                        "token_contract_id": info.token_contract_id,
+                        "governance_id": info.dao_account_id,
+                    })
+                    .to_string()
+                    .into_bytes(),
+                    NearToken::from_near(0),
+                    GAS_FOR_INIT,
+                )
+        });
+
+        let deployments = match (deploy_dao, deploy_proposals) {
+            (Some(deploy_dao), Some(deploy_proposals)) => deploy_dao.and(deploy_proposals),
+            (Some(deploy), None) | (None, Some(deploy)) => deploy,
+            (None, None) => env::panic_str("The DAO is already deployed"),
+        };
+        self.partial_daos.insert(
+            info.name.clone(),
+            PartialDao {
+                deploying: true,
+                ..partial.clone()
+            },
+        );
+        deployments.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_CREATE_CALLBACK)
+                .on_dao_created(partial, dao_deposit, proposal_deposit),
+        )
+    }
+
+    fn storage_cost(code_len: usize) -> NearToken {
+        env::storage_byte_cost().saturating_mul(code_len as u128 + ACCOUNT_STORAGE_OVERHEAD)
+    }
+}
+
+// The rest of this file holds the inline tests for the code above
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, RuntimeFeesConfig};
+
+    fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id("factory.near".parse().unwrap())
+            .signer_account_id(predecessor_account_id.clone())
+            .predecessor_account_id(predecessor_account_id);
+        builder
+    }
+
+    fn set_promise_results(context: &VMContextBuilder, results: Vec<PromiseResult>) {
+        testing_env!(
+            context.build(),
+            near_sdk::test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            results,
+        );
+    }
+
+    fn dao_info(name: &str) -> DaoInfo {
+        DaoInfo {
+            name: name.to_string(),
+            dao_account_id: format!("{}-dao.factory.near", name).parse().unwrap(),
+            proposal_contract_id: format!("{}-proposals.factory.near", name).parse().unwrap(),
+            token_contract_id: "fdao.near".parse().unwrap(),
+            admin_account_id: accounts(1),
+            created_at: 0,
+        }
+    }
+
+    fn partial_dao(name: &str) -> PartialDao {
+        PartialDao {
+            info: dao_info(name),
+            creator: accounts(1),
+            dao_deployed: false,
+            proposals_deployed: false,
+            deploying: false,
+        }
+    }
+
+    #[test]
+    fn test_new() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let contract = DaoFactory::new(accounts(0), "fdao.near".parse().unwrap());
+        assert_eq!(contract.get_owner_id(), &accounts(0));
+        assert_eq!(
+            contract.get_default_token_contract_id().as_str(),
+            "fdao.near"
+        );
+        assert_eq!(contract.get_dao_count(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner can update contract code")]
+    fn test_set_dao_code_not_owner() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = DaoFactory::new(accounts(1), "fdao.near".parse().unwrap());
+        contract.set_dao_code();
+    }
+
+    #[test]
+    #[should_panic(expected = "DAO contract code not set")]
+    fn test_create_dao_without_code() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(NearToken::from_near(10)).build());
+        let mut contract = DaoFactory::new(accounts(0), "fdao.near".parse().unwrap());
+        contract.create_dao("guild".to_string(), accounts(1), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "to deploy the DAO")]
+    fn test_create_dao_insufficient_deposit() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = DaoFactory::new(accounts(0), "fdao.near".parse().unwrap());
+        contract.dao_code.set(Some(vec![0u8; 1_000]));
+        contract.proposal_code.set(Some(vec![0u8; 1_000]));
+        testing_env!(context
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.create_dao("guild".to_string(), accounts(1), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid DAO name")]
+    fn test_create_dao_invalid_name() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = DaoFactory::new(accounts(0), "fdao.near".parse().unwrap());
+        contract.dao_code.set(Some(vec![0u8; 1_000]));
+        contract.proposal_code.set(Some(vec![0u8; 1_000]));
+        testing_env!(context.attached_deposit(NearToken::from_near(10)).build());
+        contract.create_dao("Not A Name".to_string(), accounts(1), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "A DAO with this name is being deployed or partly deployed")]
+    fn test_create_dao_twice_in_one_block() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = DaoFactory::new(accounts(0), "fdao.near".parse().unwrap());
+        contract.dao_code.set(Some(vec![0u8; 1_000]));
+        contract.proposal_code.set(Some(vec![0u8; 1_000]));
+        testing_env!(context.attached_deposit(NearToken::from_near(10)).build());
+        contract.create_dao("guild".to_string(), accounts(1), None);
+        assert!(contract.get_partial_dao("guild".to_string()).unwrap().deploying);
+        contract.create_dao("guild".to_string(), accounts(2), None);
+    }
+
+    #[test]
+    fn test_on_dao_created_records_dao() {
+        let context = get_context("factory.near".parse().unwrap());
+        testing_env!(context.build());
+        let mut contract = DaoFactory::new(accounts(0), "fdao.near".parse().unwrap());
+        set_promise_results(
+            &context,
+            vec![
+                PromiseResult::Successful(vec![]),
+                PromiseResult::Successful(vec![]),
+            ],
+        );
+        let created = contract.on_dao_created(
+            partial_dao("guild"),
+            NearToken::from_near(1),
+            NearToken::from_near(1),
+        );
+        assert!(created);
+        assert_eq!(contract.get_daos(None, None), vec![dao_info("guild")]);
+    }
+
+    #[test]
+    fn test_on_dao_created_failure_not_recorded() {
+        let context = get_context("factory.near".parse().unwrap());
+        testing_env!(context.build());
+        let mut contract = DaoFactory::new(accounts(0), "fdao.near".parse().unwrap());
+        set_promise_results(
+            &context,
+            vec![PromiseResult::Successful(vec![]), PromiseResult::Failed],
+        );
+        let created = contract.on_dao_created(
+            partial_dao("guild"),
+            NearToken::from_near(1),
+            NearToken::from_near(1),
+        );
+        assert!(!created);
+        assert_eq!(contract.get_dao_count(), 0);
+        // The deployed DAO is kept and its name stays taken until a retry completes it
+        assert_eq!(
+            contract.get_partial_dao("guild".to_string()),
+            Some(PartialDao {
+                dao_deployed: true,
+                ..partial_dao("guild")
+            })
+        );
+    }
+
+    #[test]
+    fn test_on_dao_created_both_failed_frees_name() {
+        let context = get_context("factory.near".parse().unwrap());
+        testing_env!(context.build());
+        let mut contract = DaoFactory::new(accounts(0), "fdao.near".parse().unwrap());
+        set_promise_results(
+            &context,
+            vec![PromiseResult::Failed, PromiseResult::Failed],
+        );
+        contract.on_dao_created(
+            partial_dao("guild"),
+            NearToken::from_near(1),
+            NearToken::from_near(1),
+        );
+        assert_eq!(contract.get_partial_dao("guild".to_string()), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "A DAO with this name is being deployed or partly deployed")]
+    fn test_create_partly_deployed_dao() {
+        let mut context = get_context("factory.near".parse().unwrap());
+        testing_env!(context.build());
+        let mut contract = DaoFactory::new(accounts(0), "fdao.near".parse().unwrap());
+        set_promise_results(&context, vec![PromiseResult::Failed, PromiseResult::Successful(vec![])]);
+        contract.on_dao_created(
+            partial_dao("guild"),
+            NearToken::from_near(1),
+            NearToken::from_near(1),
+        );
+        testing_env!(context.attached_deposit(NearToken::from_near(10)).build());
+        contract.create_dao("guild".to_string(), accounts(1), None);
+    }
+
+    #[test]
+    fn test_retry_dao_deploys_missing_contract() {
+        let mut context = get_context("factory.near".parse().unwrap());
+        testing_env!(context.build());
+        let mut contract = DaoFactory::new(accounts(0), "fdao.near".parse().unwrap());
+        contract.dao_code.set(Some(vec![0u8; 1_000]));
+        contract.proposal_code.set(Some(vec![0u8; 1_000]));
+        set_promise_results(&context, vec![PromiseResult::Successful(vec![]), PromiseResult::Failed]);
+        contract.on_dao_created(
+            partial_dao("guild"),
+            NearToken::from_near(1),
+            NearToken::from_near(1),
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(DaoFactory::storage_cost(1_000))
+            .build());
+        contract.retry_dao("guild".to_string());
+        let receivers: Vec<String> = near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .map(|receipt| receipt.receiver_id.to_string())
+            .collect();
+        assert_eq!(
+            receivers,
+            vec![
+                "guild-proposals.factory.near".to_string(),
+                "factory.near".to_string()
+            ]
+        );
+
+        // The callback only sees the retried deployment
+        set_promise_results(&context, vec![PromiseResult::Successful(vec![])]);
+        let retried = PartialDao {
+            creator: accounts(2),
+            dao_deployed: true,
+            ..partial_dao("guild")
+        };
+        assert!(contract.on_dao_created(retried, NearToken::from_near(0), NearToken::from_near(1)));
+        assert_eq!(contract.get_daos(None, None), vec![dao_info("guild")]);
+        assert_eq!(contract.get_partial_dao("guild".to_string()), None);
+    }
+
+    #[test]
+    fn test_get_daos_pagination() {
+        let context = get_context("factory.near".parse().unwrap());
+        testing_env!(context.build());
+        let mut contract = DaoFactory::new(accounts(0), "fdao.near".parse().unwrap());
+        set_promise_results(
+            &context,
+            vec![
+                PromiseResult::Successful(vec![]),
+                PromiseResult::Successful(vec![]),
+            ],
+        );
+        for name in ["alpha", "beta", "gamma"] {
+            contract.on_dao_created(
+                partial_dao(name),
+                NearToken::from_near(1),
+                NearToken::from_near(1),
+            );
+        }
+        assert_eq!(contract.get_dao_count(), 3);
+        let page = contract.get_daos(Some(1), Some(1));
+        assert_eq!(page, vec![dao_info("beta")]);
+        assert_eq!(contract.get_daos(Some(2), Some(10)).len(), 1);
+        assert!(contract.get_daos(Some(3), None).is_empty());
+    }
+}
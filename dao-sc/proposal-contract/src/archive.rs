@@ -0,0 +1,366 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::{env, near_bindgen, AccountId, Promise};
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize};
+use crate::conviction::ONE_YOCTO;
+use crate::vote::ft_contract;
+use crate::ProposalContract;
+use crate::ProposalContractExt;
+use crate::ProposalState;
+
+// How long a finalized proposal is kept in full after its deadline, in nanoseconds
+pub const ARCHIVE_RETENTION_PERIOD: u64 = 30 * 24 * 60 * 60 * 1_000_000_000;
+
+// Entries cleared per `archive_proposal` call when no limit is given
+const DEFAULT_ARCHIVE_BATCH: u64 = 50;
+
+// Compact record kept once a proposal has been archived
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct ArchivedProposal {
+    pub state: ProposalState,
+    pub deadline: u64,
+    // Final vote count per option, in option order
+    pub tally: Vec<u64>,
+    pub abstain_count: u64,
+    pub veto_count: u64,
+    // Index of the winning option when the proposal passed
+    pub winner: Option<u8>,
+    // sha256 of the borsh-serialized (title, description, deadline, options, minimum_votes)
+    pub content_hash: Base64VecU8,
+}
+
+#[near_bindgen]
+impl ProposalContract {
+    // Clear up to `limit` ballots, commitments, objections and comments of a finalized proposal once the
+    // retention period is over, then replace the proposal with its summary on the call that clears the last one.
+    // Anyone can call it, the freed storage is refunded to whoever paid for each entry, one transfer per account.
+    // Returns the summary once the proposal is archived, None while entries are left.
+    pub fn archive_proposal(&mut self, proposal_id: u64, limit: Option<u64>) -> Option<ArchivedProposal> {
+        self.assert_not_paused("archive_proposal");
+        let proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        assert!(proposal.state != ProposalState::Open, "Proposal must be finalized before archiving");
+        assert!(
+            env::block_timestamp() > proposal.deadline.saturating_add(ARCHIVE_RETENTION_PERIOD),
+            "Proposal retention period has not passed yet"
+        );
+        let voter_count = proposal.voter_count;
+        let commit_count = proposal.commit_count;
+        let objection_count = proposal.objection_count;
+        let comment_count = proposal.comment_count;
+        let entry_count = voter_count + commit_count + objection_count + comment_count;
+        let from = proposal.archive_cursor;
+        let to = from.saturating_add(limit.unwrap_or(DEFAULT_ARCHIVE_BATCH)).min(entry_count);
+
+        let mut freed_bytes: BTreeMap<AccountId, u64> = BTreeMap::new();
+        for entry in from..to {
+            let initial_storage_usage = env::storage_usage();
+            // Entries are cleared in order: ballots, commitments, objections, then comments
+            let payer = if entry < voter_count {
+                // Ballots go back to the account that signed and paid for them, not necessarily the voter,
+                // together with the voter's history entry. The participation stats keep counting the ballot.
+                let (voter, payer) = self.voters.remove(&(proposal_id, entry)).expect("Voter not found");
+                self.votes.remove(&(proposal_id, voter.clone()));
+                self.votes_by_account.remove(&(voter, proposal_id));
+                self.voters.flush();
+                self.votes.flush();
+                self.votes_by_account.flush();
+                Some(payer)
+            } else if entry < voter_count + commit_count {
+                // Committers paid for their commitments, revealed or not
+                let commit_index = entry - voter_count;
+                let committer = self.committers.remove(&(proposal_id, commit_index)).expect("Committer not found");
+                self.commitments.remove(&(proposal_id, committer.clone()));
+                self.committers.flush();
+                self.commitments.flush();
+                Some(committer)
+            } else if entry < voter_count + commit_count + objection_count {
+                let objection_index = entry - voter_count - commit_count;
+                let objector = self.objectors.remove(&(proposal_id, objection_index)).expect("Objector not found");
+                // FDAO still locked by an objection nobody withdrew goes back to the objector
+                if let Some(amount) = self.objections.remove(&(proposal_id, objector.clone())) {
+                    ft_contract::ext(self.token_contract_id.clone())
+                        .with_attached_deposit(ONE_YOCTO)
+                        .with_static_gas(self.gas_config.ft_transfer)
+                        .with_unused_gas_weight(0)
+                        .ft_transfer(objector.clone(), amount, None);
+                }
+                self.objectors.flush();
+                self.objections.flush();
+                Some(objector)
+            } else {
+                // Whoever paid for a comment gets back what is left of its storage, unless a moderator removed it
+                let comment_id = entry - voter_count - commit_count - objection_count;
+                let comment = self.comments.remove(&(proposal_id, comment_id));
+                self.comments.flush();
+                comment.and_then(|comment| comment.storage_payer)
+            };
+            if let Some(payer) = payer {
+                let freed = initial_storage_usage.saturating_sub(env::storage_usage());
+                *freed_bytes.entry(payer).or_default() += freed;
+            }
+        }
+
+        let proposal = self.proposals.get_mut(&proposal_id).expect("Proposal not found");
+        proposal.archive_cursor = to;
+        let summary = if to == entry_count {
+            let initial_storage_usage = env::storage_usage();
+            let summary = self.internal_archive(proposal_id);
+            *freed_bytes.entry(summary.1).or_default() += initial_storage_usage.saturating_sub(env::storage_usage());
+            env::log_str(&format!(
+                "Proposal {} archived, {} votes and {} comments cleared",
+                proposal_id, voter_count, comment_count
+            ));
+            Some(summary.0)
+        } else {
+            env::log_str(&format!("Proposal {} archiving, {} of {} entries cleared", proposal_id, to, entry_count));
+            None
+        };
+
+        for (payer, bytes) in freed_bytes {
+            let refund = env::storage_byte_cost().saturating_mul(bytes as u128);
+            if !refund.is_zero() {
+                Promise::new(payer).transfer(refund);
+            }
+        }
+        summary
+    }
+
+    // Get the summary of an archived proposal
+    pub fn get_archived_proposal(&self, proposal_id: u64) -> Option<ArchivedProposal> {
+        self.archived.get(&proposal_id).cloned()
+    }
+}
+
+impl ProposalContract {
+    // Swap a cleared proposal for its summary, returns the summary and the account that paid for the proposal
+    fn internal_archive(&mut self, proposal_id: u64) -> (ArchivedProposal, AccountId) {
+        let proposal = self.proposals.remove(&proposal_id).expect("Proposal not found");
+        let winner = if proposal.state == ProposalState::Passed {
+            proposal.winning_option()
+        } else {
+            None
+        };
+        let content = borsh::to_vec(&(
+            &proposal.title,
+            &proposal.description,
+            proposal.deadline,
+            &proposal.options,
+            proposal.minimum_votes,
+        ))
+        .expect("Failed to serialize proposal");
+        let summary = ArchivedProposal {
+            state: proposal.state.clone(),
+            deadline: proposal.deadline,
+            tally: proposal.tally.clone(),
+            abstain_count: proposal.abstain_count,
+            veto_count: proposal.veto_count,
+            winner,
+            content_hash: env::sha256(&content).into(),
+        };
+        self.unindex_proposal(proposal_id, proposal.category.as_ref(), &proposal.tags);
+        self.archived.insert(proposal_id, summary.clone());
+        self.flush_collections();
+        (summary, proposal.storage_payer)
+    }
+}
+
+// The rest of this file holds the inline tests for the code above
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ballot;
+    use near_sdk::json_types::U128;
+    use near_sdk::mock::MockAction;
+    use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+    use near_sdk::{testing_env, NearToken};
+
+    const DEADLINE: u64 = 1_000;
+
+    fn get_context(block_timestamp: u64) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id("proposal".parse().unwrap())
+            .signer_account_id("admin".parse().unwrap())
+            .predecessor_account_id("admin".parse().unwrap())
+            .account_balance(NearToken::from_near(10))
+            .attached_deposit(NearToken::from_near(1))
+            .block_timestamp(block_timestamp);
+        builder
+    }
+
+    fn setup_finalized_proposal(context: &mut VMContextBuilder) -> ProposalContract {
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        contract.create_proposal(
+            "title".to_string(),
+            "description".to_string(),
+            DEADLINE,
+            vec!["option1".to_string(), "option2".to_string()],
+            1,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        for voter in ["alice", "bob"] {
+            testing_env!(context.signer_account_id(voter.parse().unwrap()).build());
+            contract.process_vote_callback(0, voter.parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
+        }
+        testing_env!(context.block_timestamp(DEADLINE + 1).build());
+        contract.update_status(0);
+        contract
+    }
+
+    #[test]
+    fn test_archive_proposal() {
+        let mut context = get_context(0);
+        testing_env!(context.build());
+        let mut contract = setup_finalized_proposal(&mut context);
+        contract.process_comment_callback(0, "alice".parse().unwrap(), "Nice".to_string(), None, Ok(U128(1)));
+
+        testing_env!(context.block_timestamp(DEADLINE + ARCHIVE_RETENTION_PERIOD + 1).build());
+        let summary = contract.archive_proposal(0, None).unwrap();
+        assert!(contract.comments.get(&(0, 0)).is_none());
+        assert_eq!(summary.state, ProposalState::Passed);
+        assert_eq!(summary.tally, vec![2, 0]);
+        assert_eq!(summary.winner, Some(0));
+        assert!(contract.get_proposal(0).is_none());
+        assert_eq!(contract.get_vote(0, "alice".parse().unwrap()), None);
+        assert_eq!(contract.get_archived_proposal(0), Some(summary));
+        assert!(contract.get_votes_by_account("alice".parse().unwrap(), None, None).is_empty());
+        assert_eq!(contract.get_participation_stats("alice".parse().unwrap()).proposals_voted, 1);
+    }
+
+    #[test]
+    fn test_archive_refunds_comment_payer() {
+        let mut context = get_context(0);
+        testing_env!(context.build());
+        let mut contract = setup_finalized_proposal(&mut context);
+        // A relayer signs and pays for carol's comment
+        testing_env!(context.signer_account_id("relayer".parse().unwrap()).build());
+        contract.process_comment_callback(0, "carol".parse().unwrap(), "Nice".to_string(), None, Ok(U128(1)));
+
+        testing_env!(context.block_timestamp(DEADLINE + ARCHIVE_RETENTION_PERIOD + 1).build());
+        contract.archive_proposal(0, None);
+        let refunded: Vec<String> = get_created_receipts()
+            .iter()
+            .filter(|receipt| receipt.actions.iter().any(|action| matches!(action, MockAction::Transfer { .. })))
+            .map(|receipt| receipt.receiver_id.to_string())
+            .collect();
+        assert!(refunded.contains(&"relayer".to_string()));
+        assert!(!refunded.contains(&"carol".to_string()));
+    }
+
+    #[test]
+    fn test_archive_in_batches() {
+        let mut context = get_context(0);
+        testing_env!(context.build());
+        let mut contract = setup_finalized_proposal(&mut context);
+        testing_env!(context.signer_account_id("alice".parse().unwrap()).build());
+        for body in ["First", "Second"] {
+            contract.process_comment_callback(0, "alice".parse().unwrap(), body.to_string(), None, Ok(U128(1)));
+        }
+
+        testing_env!(context.block_timestamp(DEADLINE + ARCHIVE_RETENTION_PERIOD + 1).build());
+        assert_eq!(contract.archive_proposal(0, Some(2)), None);
+        assert_eq!(contract.get_vote(0, "bob".parse().unwrap()), None);
+        assert!(contract.get_proposal(0).is_some());
+
+        // Both comments and the proposal go in one batch, with a single refund to alice
+        testing_env!(context.build());
+        assert!(contract.archive_proposal(0, None).is_some());
+        let transfers_to = |account: &str| {
+            get_created_receipts()
+                .iter()
+                .filter(|receipt| receipt.receiver_id.as_str() == account)
+                .filter(|receipt| receipt.actions.iter().any(|action| matches!(action, MockAction::Transfer { .. })))
+                .count()
+        };
+        assert_eq!(transfers_to("alice"), 1);
+        assert_eq!(transfers_to("admin"), 1);
+        assert!(contract.get_proposal(0).is_none());
+    }
+
+    #[test]
+    fn test_archive_refunds_ballot_payer() {
+        let mut context = get_context(0);
+        testing_env!(context.build());
+        let mut contract = setup_finalized_proposal(&mut context);
+        testing_env!(context.block_timestamp(0).signer_account_id("admin".parse().unwrap()).build());
+        contract.create_proposal(
+            "title".to_string(),
+            "description".to_string(),
+            DEADLINE,
+            vec!["option1".to_string(), "option2".to_string()],
+            1,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        // A relayer signs and pays for carol's ballot
+        testing_env!(context.signer_account_id("relayer".parse().unwrap()).build());
+        contract.process_vote_callback(1, "carol".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
+        testing_env!(context.block_timestamp(DEADLINE + 1).signer_account_id("admin".parse().unwrap()).build());
+        contract.update_status(1);
+
+        testing_env!(context.block_timestamp(DEADLINE + ARCHIVE_RETENTION_PERIOD + 1).build());
+        contract.archive_proposal(1, None);
+        let refunded: Vec<String> = get_created_receipts()
+            .iter()
+            .filter(|receipt| receipt.actions.iter().any(|action| matches!(action, MockAction::Transfer { .. })))
+            .map(|receipt| receipt.receiver_id.to_string())
+            .collect();
+        assert!(refunded.contains(&"relayer".to_string()));
+        assert!(!refunded.contains(&"carol".to_string()));
+    }
+
+    #[test]
+    fn test_archive_proposal_content_hash() {
+        let mut context = get_context(0);
+        testing_env!(context.build());
+        let mut contract = setup_finalized_proposal(&mut context);
+
+        testing_env!(context.block_timestamp(DEADLINE + ARCHIVE_RETENTION_PERIOD + 1).build());
+        let summary = contract.archive_proposal(0, None).unwrap();
+        let options = vec!["option1".to_string(), "option2".to_string()];
+        let content = borsh::to_vec(&("title", "description", DEADLINE, options, 1u8)).unwrap();
+        assert_eq!(summary.content_hash.0, env::sha256(&content));
+    }
+
+    #[test]
+    #[should_panic(expected = "Proposal retention period has not passed yet")]
+    fn test_archive_proposal_within_retention() {
+        let mut context = get_context(0);
+        testing_env!(context.build());
+        let mut contract = setup_finalized_proposal(&mut context);
+        contract.archive_proposal(0, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Proposal must be finalized before archiving")]
+    fn test_archive_open_proposal() {
+        let context = get_context(0);
+        testing_env!(context.build());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        contract.create_proposal(
+            "title".to_string(),
+            "description".to_string(),
+            DEADLINE,
+            vec!["option1".to_string(), "option2".to_string()],
+            1,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        contract.archive_proposal(0, None);
+    }
+}
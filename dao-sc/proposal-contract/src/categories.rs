@@ -0,0 +1,166 @@
+use near_sdk::{env, near_bindgen};
+use serde::{Deserialize, Serialize};
+use crate::ProposalContract;
+use crate::ProposalContractExt;
+use crate::ProposalState;
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+
+// Proposal fields returned by the category and tag listings
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct ProposalSummary {
+    pub id: u64,
+    pub title: String,
+    pub description: String,
+    pub deadline: u64,
+    pub state: ProposalState,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+}
+
+#[near_bindgen]
+impl ProposalContract {
+    // Allowed categories are managed by governance, see `set_governance`
+    pub fn add_category(&mut self, category: String) {
+        self.assert_governance();
+        assert!(!category.trim().is_empty(), "Category cannot be empty");
+        env::log_str(&format!("Category {} added", category));
+        self.categories.insert(category);
+    }
+
+    // Existing proposals keep the category, it just cannot be used for new ones
+    pub fn remove_category(&mut self, category: String) {
+        self.assert_governance();
+        env::log_str(&format!("Category {} removed", category));
+        self.categories.remove(&category);
+    }
+
+    pub fn get_categories(&self) -> Vec<String> {
+        self.categories.iter().cloned().collect()
+    }
+
+    // List the proposals of a category by id, starting at `from_id`
+    pub fn list_proposals_by_category(&self, category: String, from_id: Option<u64>, limit: Option<u64>) -> Vec<ProposalSummary> {
+        let from = (category.clone(), from_id.unwrap_or(0));
+        let to = (category, u64::MAX);
+        self.proposals_by_category
+            .range(from..=to)
+            .take(limit.unwrap_or(DEFAULT_PAGE_SIZE) as usize)
+            .filter_map(|((_category, proposal_id), _)| self.get_proposal_summary(*proposal_id))
+            .collect()
+    }
+
+    // List the proposals carrying a tag by id, starting at `from_id`
+    pub fn list_proposals_by_tag(&self, tag: String, from_id: Option<u64>, limit: Option<u64>) -> Vec<ProposalSummary> {
+        let from = (tag.clone(), from_id.unwrap_or(0));
+        let to = (tag, u64::MAX);
+        self.proposals_by_tag
+            .range(from..=to)
+            .take(limit.unwrap_or(DEFAULT_PAGE_SIZE) as usize)
+            .filter_map(|((_tag, proposal_id), _)| self.get_proposal_summary(*proposal_id))
+            .collect()
+    }
+
+    pub fn get_proposal_summary(&self, proposal_id: u64) -> Option<ProposalSummary> {
+        self.proposals.get(&proposal_id).map(|proposal| ProposalSummary {
+            id: proposal_id,
+            title: proposal.title.clone(),
+            description: proposal.description.clone(),
+            deadline: proposal.deadline,
+            state: proposal.state.clone(),
+            category: proposal.category.clone(),
+            tags: proposal.tags.clone(),
+        })
+    }
+}
+
+impl ProposalContract {
+    pub(crate) fn assert_valid_category(&self, category: Option<&String>) {
+        if let Some(category) = category {
+            assert!(self.categories.contains(category), "Unknown category: {}", category);
+        }
+    }
+
+    pub(crate) fn index_proposal(&mut self, proposal_id: u64, category: Option<&String>, tags: &[String]) {
+        if let Some(category) = category {
+            self.proposals_by_category.insert((category.clone(), proposal_id), ());
+        }
+        for tag in tags {
+            self.proposals_by_tag.insert((tag.clone(), proposal_id), ());
+        }
+    }
+
+    pub(crate) fn unindex_proposal(&mut self, proposal_id: u64, category: Option<&String>, tags: &[String]) {
+        if let Some(category) = category {
+            self.proposals_by_category.remove(&(category.clone(), proposal_id));
+        }
+        for tag in tags {
+            self.proposals_by_tag.remove(&(tag.clone(), proposal_id));
+        }
+    }
+}
+
+// The rest of this file holds the inline tests for the code above
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{testing_env, NearToken};
+
+    fn get_context() -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id("proposal".parse().unwrap())
+            .signer_account_id("proposal".parse().unwrap())
+            .predecessor_account_id("proposal".parse().unwrap())
+            .account_balance(NearToken::from_near(10))
+            .attached_deposit(NearToken::from_near(1))
+            .block_timestamp(0);
+        builder
+    }
+
+    fn create(contract: &mut ProposalContract, title: &str, category: Option<&str>, tags: &[&str]) -> u64 {
+        contract.create_proposal(
+            title.to_string(),
+            "description".to_string(),
+            1000,
+            vec!["Yes".to_string(), "No".to_string()],
+            1,
+            None,
+            None,
+            category.map(|category| category.to_string()),
+            Some(tags.iter().map(|tag| tag.to_string()).collect()),
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_list_proposals_by_category_and_tag() {
+        testing_env!(get_context().build());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        contract.add_category("treasury".to_string());
+        contract.add_category("technical".to_string());
+        create(&mut contract, "Grant", Some("treasury"), &["grants", "q3"]);
+        create(&mut contract, "Upgrade", Some("technical"), &["q3"]);
+        create(&mut contract, "Budget", Some("treasury"), &[]);
+
+        let treasury = contract.list_proposals_by_category("treasury".to_string(), None, None);
+        assert_eq!(treasury.iter().map(|summary| summary.id).collect::<Vec<_>>(), vec![0, 2]);
+        assert_eq!(treasury[0].tags, vec!["grants".to_string(), "q3".to_string()]);
+        let q3 = contract.list_proposals_by_tag("q3".to_string(), Some(1), Some(10));
+        assert_eq!(q3.len(), 1);
+        assert_eq!(q3[0].title, "Upgrade");
+        assert!(contract.list_proposals_by_tag("missing".to_string(), None, None).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown category: community")]
+    fn test_create_proposal_unknown_category() {
+        testing_env!(get_context().build());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        contract.add_category("treasury".to_string());
+        create(&mut contract, "Meetup", Some("community"), &[]);
+    }
+}
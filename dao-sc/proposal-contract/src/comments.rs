@@ -0,0 +1,290 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, AccountId, Promise, PromiseError};
+use serde::{Deserialize, Serialize};
+use crate::storage::MIN_COMMENT_DEPOSIT;
+use crate::ProposalContract;
+use crate::ProposalContractExt;
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+
+// A comment on a proposal, replies point to the id of their parent comment
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Comment {
+    pub id: u64,
+    pub author: AccountId,
+    pub body: String,
+    pub reply_to: Option<u64>,
+    pub created_at: u64,
+    // Removed comments keep their place in the thread with an empty body
+    pub removed: bool,
+    // Account that paid for the comment storage and gets it back when the storage is freed,
+    // None once a moderator removed the comment and the deposit was forfeited
+    pub storage_payer: Option<AccountId>,
+}
+
+#[near_bindgen]
+impl ProposalContract {
+    // Comment on a proposal as a token holder, the attached deposit pays for the comment storage
+    #[payable]
+    pub fn add_comment(&mut self, proposal_id: u64, body: String, reply_to: Option<u64>) -> Promise {
+        self.assert_not_paused("add_comment");
+        let deposit = env::attached_deposit();
+        assert!(deposit >= MIN_COMMENT_DEPOSIT, "Attach at least {} to cover the comment storage", MIN_COMMENT_DEPOSIT);
+        self.assert_valid_comment(proposal_id, &body, reply_to);
+        let author = env::predecessor_account_id();
+        let promise = self.query_voting_power(author.clone());
+
+        promise.then(
+            Self::ext(env::current_account_id())
+                .with_attached_deposit(deposit)
+                .with_static_gas(self.gas_config.comment_callback)
+                .with_unused_gas_weight(1)
+                .process_comment_callback(proposal_id, author, body, reply_to),
+        )
+    }
+
+    #[private]
+    pub fn process_comment_callback(
+        &mut self,
+        proposal_id: u64,
+        author: AccountId,
+        body: String,
+        reply_to: Option<u64>,
+        #[callback_result] balance: Result<U128, PromiseError>,
+    ) -> Option<u64> {
+        match balance {
+            Ok(balance) => {
+                if balance.0 == 0 {
+                    Self::refund_deposit("Only token holders can comment");
+                    return None;
+                }
+                // The proposal may have changed while the balance was fetched
+                self.assert_valid_comment(proposal_id, &body, reply_to);
+                let initial_storage_usage = env::storage_usage();
+                let proposal = self.proposals.get_mut(&proposal_id).expect("Proposal not found");
+                let comment_id = proposal.comment_count;
+                proposal.comment_count += 1;
+                let comment = Comment {
+                    id: comment_id,
+                    author: author.clone(),
+                    body,
+                    reply_to,
+                    created_at: env::block_timestamp(),
+                    removed: false,
+                    storage_payer: Some(env::signer_account_id()),
+                };
+                self.comments.insert((proposal_id, comment_id), comment);
+                self.flush_collections();
+                Self::charge_storage(initial_storage_usage, &env::signer_account_id());
+
+                env::log_str(&format!("Comment {} added to proposal {} by {}", comment_id, proposal_id, author));
+                Some(comment_id)
+            }
+            Err(e) => {
+                Self::refund_deposit(&format!("Failed to retrieve balance: {:?}", e));
+                None
+            }
+        }
+    }
+
+    // Hide a comment, only moderators can call it. The storage deposit is forfeited to the contract,
+    // so that posting spam costs the spammer every time it is removed.
+    pub fn remove_comment(&mut self, proposal_id: u64, comment_id: u64) {
+        let moderator = env::predecessor_account_id();
+        assert!(self.moderators.contains(&moderator), "Only moderators can remove comments");
+        let comment = self.comments.get_mut(&(proposal_id, comment_id)).expect("Comment not found");
+        assert!(!comment.removed, "Comment already removed");
+        comment.body = String::new();
+        comment.removed = true;
+        comment.storage_payer = None;
+        env::log_str(&format!("Comment {} on proposal {} removed by {}", comment_id, proposal_id, moderator));
+    }
+
+    // Authors can take their own comments down, the freed storage is refunded to whoever paid for it
+    pub fn delete_comment(&mut self, proposal_id: u64, comment_id: u64) {
+        let initial_storage_usage = env::storage_usage();
+        let comment = self.comments.get_mut(&(proposal_id, comment_id)).expect("Comment not found");
+        assert!(comment.author == env::predecessor_account_id(), "Only the author can delete a comment");
+        assert!(!comment.removed, "Comment already removed");
+        comment.body = String::new();
+        comment.removed = true;
+        let storage_payer = comment.storage_payer.clone();
+        self.flush_collections();
+        if let Some(storage_payer) = storage_payer {
+            Self::release_storage(initial_storage_usage, &storage_payer);
+        }
+        env::log_str(&format!("Comment {} on proposal {} deleted by its author", comment_id, proposal_id));
+    }
+
+    // Get the comments of a proposal in posting order
+    pub fn get_comments(&self, proposal_id: u64, from_index: Option<u64>, limit: Option<u64>) -> Vec<Comment> {
+        let proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        let from_index = from_index.unwrap_or(0);
+        let end = from_index.saturating_add(limit.unwrap_or(DEFAULT_PAGE_SIZE)).min(proposal.comment_count);
+        (from_index..end)
+            .filter_map(|comment_id| self.comments.get(&(proposal_id, comment_id)).cloned())
+            .collect()
+    }
+
+    // Moderators are managed by governance, see `set_governance`
+    pub fn add_moderator(&mut self, account_id: AccountId) {
+        self.assert_governance();
+        env::log_str(&format!("Moderator {} added", account_id));
+        self.moderators.insert(account_id);
+    }
+
+    pub fn remove_moderator(&mut self, account_id: AccountId) {
+        self.assert_governance();
+        env::log_str(&format!("Moderator {} removed", account_id));
+        self.moderators.remove(&account_id);
+    }
+
+    pub fn get_moderators(&self) -> Vec<AccountId> {
+        self.moderators.iter().cloned().collect()
+    }
+}
+
+impl ProposalContract {
+    fn assert_valid_comment(&self, proposal_id: u64, body: &str, reply_to: Option<u64>) {
+        let proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        self.limits.assert_valid_comment(body);
+        if let Some(parent_id) = reply_to {
+            assert!(parent_id < proposal.comment_count, "Parent comment not found");
+        }
+    }
+}
+
+// The rest of this file holds the inline tests for the code above
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+    use near_sdk::{testing_env, NearToken};
+
+    fn get_context(predecessor: &str) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id("proposal".parse().unwrap())
+            .signer_account_id(predecessor.parse().unwrap())
+            .predecessor_account_id(predecessor.parse().unwrap())
+            .account_balance(NearToken::from_near(10))
+            .attached_deposit(NearToken::from_near(1))
+            .block_timestamp(0);
+        builder
+    }
+
+    fn setup_contract() -> ProposalContract {
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        contract.create_proposal(
+            "title".to_string(),
+            "description".to_string(),
+            1000,
+            vec!["option1".to_string(), "option2".to_string()],
+            1,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        contract
+    }
+
+    #[test]
+    fn test_add_comment_thread() {
+        let context = get_context("alice");
+        testing_env!(context.build());
+        let mut contract = setup_contract();
+        let first = contract
+            .process_comment_callback(0, "alice".parse().unwrap(), "First".to_string(), None, Ok(U128(1)))
+            .unwrap();
+        let reply = contract
+            .process_comment_callback(0, "bob".parse().unwrap(), "Reply".to_string(), Some(first), Ok(U128(1)))
+            .unwrap();
+
+        let comments = contract.get_comments(0, None, None);
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[1].id, reply);
+        assert_eq!(comments[1].reply_to, Some(first));
+        assert_eq!(comments[1].author, "bob".parse::<AccountId>().unwrap());
+        assert_eq!(contract.get_comments(0, Some(1), Some(5)), vec![comments[1].clone()]);
+    }
+
+    #[test]
+    fn test_add_comment_without_tokens() {
+        let context = get_context("alice");
+        testing_env!(context.build());
+        let mut contract = setup_contract();
+        let comment_id = contract.process_comment_callback(0, "alice".parse().unwrap(), "Hello".to_string(), None, Ok(U128(0)));
+        assert_eq!(comment_id, None);
+        assert!(contract.get_comments(0, None, None).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Parent comment not found")]
+    fn test_reply_to_missing_comment() {
+        let context = get_context("alice");
+        testing_env!(context.build());
+        let mut contract = setup_contract();
+        contract.add_comment(0, "Hello".to_string(), Some(0));
+    }
+
+    #[test]
+    fn test_moderator_removes_comment() {
+        let mut context = get_context("alice");
+        testing_env!(context.build());
+        let mut contract = setup_contract();
+        contract.process_comment_callback(0, "alice".parse().unwrap(), "Spam".to_string(), None, Ok(U128(1)));
+        testing_env!(context.predecessor_account_id("proposal".parse().unwrap()).build());
+        contract.add_moderator("council".parse().unwrap());
+
+        testing_env!(context.predecessor_account_id("council".parse().unwrap()).build());
+        contract.remove_comment(0, 0);
+        let comment = &contract.get_comments(0, None, None)[0];
+        assert!(comment.removed);
+        assert!(comment.body.is_empty());
+        // The deposit is forfeited, nothing goes back to the author
+        assert_eq!(comment.storage_payer, None);
+        assert!(get_created_receipts().is_empty());
+    }
+
+    #[test]
+    fn test_author_deletes_comment() {
+        let context = get_context("alice");
+        testing_env!(context.build());
+        let mut contract = setup_contract();
+        contract.process_comment_callback(0, "alice".parse().unwrap(), "Posted too early".to_string(), None, Ok(U128(1)));
+        testing_env!(context.build());
+        contract.delete_comment(0, 0);
+        assert!(contract.get_comments(0, None, None)[0].removed);
+        let refunded: Vec<String> = get_created_receipts()
+            .iter()
+            .map(|receipt| receipt.receiver_id.to_string())
+            .collect();
+        assert_eq!(refunded, vec!["alice".to_string()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the author can delete a comment")]
+    fn test_delete_comment_not_author() {
+        let mut context = get_context("alice");
+        testing_env!(context.build());
+        let mut contract = setup_contract();
+        contract.process_comment_callback(0, "alice".parse().unwrap(), "Hello".to_string(), None, Ok(U128(1)));
+        testing_env!(context.predecessor_account_id("mallory".parse().unwrap()).build());
+        contract.delete_comment(0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only moderators can remove comments")]
+    fn test_remove_comment_not_moderator() {
+        let context = get_context("alice");
+        testing_env!(context.build());
+        let mut contract = setup_contract();
+        contract.process_comment_callback(0, "alice".parse().unwrap(), "Hello".to_string(), None, Ok(U128(1)));
+        contract.remove_comment(0, 0);
+    }
+}
@@ -0,0 +1,210 @@
+use near_sdk::json_types::{Base64VecU8, U128};
+use near_sdk::{env, near_bindgen, AccountId, Promise, PromiseError};
+use crate::storage::MIN_VOTE_DEPOSIT;
+use crate::vote::Ballot;
+use crate::ProposalContract;
+use crate::ProposalContractExt;
+use crate::VotingMode;
+
+// Commitment a voter submits during the commit phase: sha256(option || salt)
+pub fn commitment_hash(vote_option: u8, salt: &str) -> Vec<u8> {
+    let mut preimage = vec![vote_option];
+    preimage.extend_from_slice(salt.as_bytes());
+    env::sha256(&preimage)
+}
+
+#[near_bindgen]
+impl ProposalContract {
+    // Commit to a hidden option before the commit deadline, the deposit pays for the commitment storage
+    #[payable]
+    pub fn commit_vote(&mut self, proposal_id: u64, commitment: Base64VecU8) -> Promise {
+        self.assert_not_paused("commit_vote");
+        let deposit = env::attached_deposit();
+        assert!(deposit >= MIN_VOTE_DEPOSIT, "Attach at least {} to cover the vote storage", MIN_VOTE_DEPOSIT);
+        assert!(commitment.0.len() == 32, "Commitment must be a 32 byte sha256 digest");
+        let voter = env::predecessor_account_id();
+        self.assert_can_commit(proposal_id, &voter);
+        let promise = self.query_voting_power(voter.clone());
+
+        promise.then(
+            Self::ext(env::current_account_id())
+                .with_attached_deposit(deposit)
+                .with_static_gas(self.gas_config.vote_callback)
+                .with_unused_gas_weight(1)
+                .process_commit_callback(proposal_id, voter, commitment),
+        )
+    }
+
+    #[private]
+    pub fn process_commit_callback(
+        &mut self,
+        proposal_id: u64,
+        voter: AccountId,
+        commitment: Base64VecU8,
+        #[callback_result] balance: Result<U128, PromiseError>,
+    ) {
+        match balance {
+            Ok(balance) => {
+                if balance.0 == 0 {
+                    return Self::refund_deposit("Insufficient balance to vote");
+                }
+                let initial_storage_usage = env::storage_usage();
+                // The proposal may have changed while the voting power was fetched
+                self.assert_can_commit(proposal_id, &voter);
+                let commitment_key = (proposal_id, voter.clone());
+                let proposal = self.proposals.get_mut(&proposal_id).expect("Proposal not found");
+                let commit_index = proposal.commit_count;
+                proposal.commit_count += 1;
+                self.commitments.insert(commitment_key, commitment);
+                self.committers.insert((proposal_id, commit_index), voter.clone());
+                self.flush_collections();
+                Self::charge_storage(initial_storage_usage, &env::signer_account_id());
+
+                env::log_str(&format!("Vote committed by {} on proposal {}", voter, proposal_id));
+            }
+            Err(e) => {
+                Self::refund_deposit(&format!("Failed to retrieve balance: {:?}", e));
+            }
+        }
+    }
+
+    // Reveal a committed option between the commit deadline and the proposal deadline
+    #[payable]
+    pub fn reveal_vote(&mut self, proposal_id: u64, vote_option: u8, salt: String) {
+        self.assert_not_paused("reveal_vote");
+        let voter = env::predecessor_account_id();
+        let initial_storage_usage = env::storage_usage();
+        let proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        let commit_deadline = match proposal.voting_mode {
+            VotingMode::CommitReveal { commit_deadline } => commit_deadline,
+            _ => env::panic_str("Proposal does not use commit-reveal voting"),
+        };
+        let now = env::block_timestamp();
+        assert!(now > commit_deadline, "Reveal phase has not started yet");
+        assert!(now <= proposal.deadline, "Voting period has ended");
+        let commitment = self.commitments.get(&(proposal_id, voter.clone())).expect("No commitment found");
+        assert!(commitment.0 == commitment_hash(vote_option, &salt), "Reveal does not match the commitment");
+
+        self.register_ballot(proposal_id, &voter, &voter, Ballot::Option(vote_option), 1);
+        self.flush_collections();
+        Self::charge_storage(initial_storage_usage, &voter);
+        env::log_str(&format!("Vote revealed by {} for option {}", voter, vote_option));
+    }
+
+    pub fn get_commitment(&self, proposal_id: u64, voter: AccountId) -> Option<Base64VecU8> {
+        self.commitments.get(&(proposal_id, voter)).cloned()
+    }
+}
+
+impl ProposalContract {
+    fn assert_can_commit(&self, proposal_id: u64, voter: &AccountId) {
+        assert!(!self.commitments.contains_key(&(proposal_id, voter.clone())), "Voter has already committed");
+        let proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        let commit_deadline = match proposal.voting_mode {
+            VotingMode::CommitReveal { commit_deadline } => commit_deadline,
+            _ => env::panic_str("Proposal does not use commit-reveal voting"),
+        };
+        assert!(env::block_timestamp() <= commit_deadline, "Commit phase has ended");
+    }
+}
+
+// The rest of this file holds the inline tests for the code above
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{testing_env, NearToken};
+
+    const COMMIT_DEADLINE: u64 = 500;
+    const DEADLINE: u64 = 1_000;
+
+    fn get_context(voter: &str, block_timestamp: u64) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id("proposal".parse().unwrap())
+            .signer_account_id(voter.parse().unwrap())
+            .predecessor_account_id(voter.parse().unwrap())
+            .account_balance(NearToken::from_near(10))
+            .attached_deposit(NearToken::from_near(1))
+            .block_timestamp(block_timestamp);
+        builder
+    }
+
+    fn setup_contract() -> ProposalContract {
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        contract.create_proposal(
+            "title".to_string(),
+            "description".to_string(),
+            DEADLINE,
+            vec!["Yes".to_string(), "No".to_string()],
+            1,
+            None,
+            None,
+            None,
+            None,
+            Some(VotingMode::CommitReveal { commit_deadline: COMMIT_DEADLINE }),
+            None,
+        );
+        contract
+    }
+
+    fn commit(contract: &mut ProposalContract, voter: &str, vote_option: u8, salt: &str) {
+        testing_env!(get_context(voter, 0).build());
+        contract.process_commit_callback(0, voter.parse().unwrap(), commitment_hash(vote_option, salt).into(), Ok(U128(1)));
+    }
+
+    #[test]
+    fn test_commit_and_reveal() {
+        testing_env!(get_context("alice", 0).build());
+        let mut contract = setup_contract();
+        commit(&mut contract, "alice", 1, "alice-salt");
+        commit(&mut contract, "bob", 0, "bob-salt");
+        assert_eq!(contract.get_votes(0), vec![("Yes".to_string(), 0), ("No".to_string(), 0)]);
+
+        testing_env!(get_context("alice", COMMIT_DEADLINE + 1).build());
+        contract.reveal_vote(0, 1, "alice-salt".to_string());
+        assert_eq!(contract.get_vote(0, "alice".parse().unwrap()), Some(Ballot::Option(1)));
+
+        // Bob never reveals, so his commitment does not count
+        testing_env!(get_context("alice", DEADLINE + 1).build());
+        assert_eq!(contract.update_status(0), crate::ProposalState::Passed);
+        assert_eq!(contract.get_votes(0), vec![("Yes".to_string(), 0), ("No".to_string(), 1)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Reveal does not match the commitment")]
+    fn test_reveal_with_wrong_salt() {
+        testing_env!(get_context("alice", 0).build());
+        let mut contract = setup_contract();
+        commit(&mut contract, "alice", 1, "alice-salt");
+        testing_env!(get_context("alice", COMMIT_DEADLINE + 1).build());
+        contract.reveal_vote(0, 1, "other-salt".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Reveal phase has not started yet")]
+    fn test_reveal_during_commit_phase() {
+        testing_env!(get_context("alice", 0).build());
+        let mut contract = setup_contract();
+        commit(&mut contract, "alice", 1, "alice-salt");
+        contract.reveal_vote(0, 1, "alice-salt".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Commit phase has ended")]
+    fn test_commit_after_commit_deadline() {
+        testing_env!(get_context("alice", 0).build());
+        let mut contract = setup_contract();
+        testing_env!(get_context("alice", COMMIT_DEADLINE + 1).build());
+        contract.process_commit_callback(0, "alice".parse().unwrap(), commitment_hash(0, "salt").into(), Ok(U128(1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Proposal uses commit-reveal voting")]
+    fn test_open_vote_on_commit_reveal_proposal() {
+        testing_env!(get_context("alice", 0).build());
+        let mut contract = setup_contract();
+        contract.process_vote_callback(0, "alice".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
+    }
+}
@@ -0,0 +1,646 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, serde_json, AccountId, NearToken, Promise, PromiseError, PromiseOrValue};
+use serde::{Deserialize, Serialize};
+use crate::vote::ft_contract;
+use crate::{ProposalContract, ProposalContractExt, ProposalState};
+
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+const DEFAULT_PAGE_SIZE: u64 = 50;
+pub(crate) const ONE_YOCTO: NearToken = NearToken::from_yoctonear(1);
+
+// Fixed point scale of the conviction parameters, 1_000_000_000 is 1.0
+pub const CONVICTION_SCALE: u128 = 1_000_000_000;
+
+// Parameters of conviction voting, fractions are scaled by CONVICTION_SCALE
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct ConvictionConfig {
+    // Conviction accrues once per period, in nanoseconds
+    pub period: u64,
+    // Share of the conviction kept from one period to the next
+    pub decay: u32,
+    // Largest share of the treasury a proposal can request
+    pub max_ratio: u32,
+    // Scales the threshold, higher values need more stake to pass
+    pub weight: u32,
+    // Account whose FDAO balance is the treasury size, read by `refresh_treasury`
+    pub treasury_account_id: Option<AccountId>,
+}
+
+impl Default for ConvictionConfig {
+    fn default() -> Self {
+        Self {
+            period: NANOS_PER_DAY,
+            decay: 800_000_000,
+            max_ratio: 200_000_000,
+            weight: 2_500_000,
+            treasury_account_id: None,
+        }
+    }
+}
+
+// Funding request that passes once enough conviction has built up behind it
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct ConvictionProposal {
+    pub id: u64,
+    pub title: String,
+    pub description: String,
+    pub proposer: AccountId,
+    pub beneficiary: AccountId,
+    pub requested_amount: U128,
+    pub total_staked: U128,
+    // Conviction as of the start of `last_period`, use `get_conviction` for the current value
+    pub conviction: U128,
+    pub last_period: u64,
+    pub state: ProposalState,
+    pub passed_at: Option<u64>,
+}
+
+// `msg` of the `ft_transfer_call` that stakes on a conviction proposal or objects to an optimistic one
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum TransferMessage {
+    Stake { proposal_id: u64 },
+    Object { object_to: u64 },
+}
+
+// value * numerator / denominator without overflowing on large token amounts, saturating instead
+fn mul_div(value: u128, numerator: u128, denominator: u128) -> u128 {
+    (value / denominator)
+        .saturating_mul(numerator)
+        .saturating_add(value % denominator * numerator / denominator)
+}
+
+// base^exponent in fixed point, for base <= CONVICTION_SCALE
+fn pow_scaled(mut base: u128, mut exponent: u64) -> u128 {
+    let mut result = CONVICTION_SCALE;
+    while exponent > 0 && result > 0 {
+        if exponent & 1 == 1 {
+            result = result * base / CONVICTION_SCALE;
+        }
+        base = base * base / CONVICTION_SCALE;
+        exponent >>= 1;
+    }
+    result
+}
+
+impl ConvictionConfig {
+    fn assert_valid(&self) {
+        assert!(self.period > 0, "Conviction period must be positive");
+        assert!((self.decay as u128) < CONVICTION_SCALE, "Decay must be below {}", CONVICTION_SCALE);
+        assert!(
+            self.max_ratio > 0 && self.max_ratio as u128 <= CONVICTION_SCALE,
+            "Max ratio must be between 1 and {}",
+            CONVICTION_SCALE
+        );
+    }
+
+    fn current_period(&self) -> u64 {
+        env::block_timestamp() / self.period
+    }
+
+    // Conviction after `periods` more periods at a constant stake:
+    // decay^n * conviction + stake * (1 - decay^n) / (1 - decay)
+    fn accrue(&self, conviction: u128, staked: u128, periods: u64) -> u128 {
+        if periods == 0 {
+            return conviction;
+        }
+        let kept = pow_scaled(self.decay as u128, periods);
+        let growth = mul_div(staked, CONVICTION_SCALE - kept, CONVICTION_SCALE - self.decay as u128);
+        mul_div(conviction, kept, CONVICTION_SCALE).saturating_add(growth)
+    }
+
+    // weight * supply / (max_ratio - requested / treasury)^2 / (1 - decay), None when the request is
+    // too large a share of the treasury to ever pass or the treasury and supply were never read
+    fn threshold(&self, requested: u128, treasury: u128, supply: u128) -> Option<u128> {
+        if treasury == 0 || supply == 0 {
+            return None;
+        }
+        let ratio = mul_div(requested, CONVICTION_SCALE, treasury);
+        let max_ratio = self.max_ratio as u128;
+        if ratio >= max_ratio {
+            return None;
+        }
+        let distance = max_ratio - ratio;
+        let scaled = mul_div(mul_div(supply, CONVICTION_SCALE, distance), CONVICTION_SCALE, distance);
+        Some(mul_div(scaled, self.weight as u128, CONVICTION_SCALE - self.decay as u128))
+    }
+}
+
+#[near_bindgen]
+impl ProposalContract {
+    // Request `requested_amount` FDAO from the treasury for `beneficiary`, the deposit pays for the storage
+    #[payable]
+    pub fn create_conviction_proposal(
+        &mut self,
+        title: String,
+        description: String,
+        beneficiary: AccountId,
+        requested_amount: U128,
+    ) -> u64 {
+        self.assert_not_paused("create_proposal");
+        let initial_storage_usage = env::storage_usage();
+        let proposer = env::signer_account_id();
+        assert!(title.len() <= self.limits.max_title_length as usize, "Title exceeds {} bytes", self.limits.max_title_length);
+        assert!(
+            description.len() <= self.limits.max_description_length as usize,
+            "Description exceeds {} bytes",
+            self.limits.max_description_length
+        );
+        assert!(requested_amount.0 > 0, "Requested amount must be positive");
+
+        let proposal_id = self.conviction_proposal_count;
+        let proposal = ConvictionProposal {
+            id: proposal_id,
+            title,
+            description,
+            proposer: proposer.clone(),
+            beneficiary,
+            requested_amount,
+            total_staked: U128(0),
+            conviction: U128(0),
+            last_period: self.conviction_config.current_period(),
+            state: ProposalState::Open,
+            passed_at: None,
+        };
+        self.conviction_proposals.insert(proposal_id, proposal);
+        self.conviction_proposal_count += 1;
+        self.flush_collections();
+        Self::charge_storage(initial_storage_usage, &proposer);
+        env::log_str(&format!("Conviction proposal {} created", proposal_id));
+        proposal_id
+    }
+
+    // NEP-141 receiver, FDAO sent with `{"proposal_id": N}` as `msg` is staked on conviction proposal N
+    // and with `{"object_to": N}` it objects to optimistic proposal N. The entries are paid from the
+    // sender's stake storage deposit, any panic refunds the tokens.
+    pub fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        assert!(env::predecessor_account_id() == self.token_contract_id, "Only the voting token can be staked");
+        let message: TransferMessage = serde_json::from_str(&msg).expect("Invalid transfer message");
+        let initial_storage_usage = env::storage_usage();
+        match message {
+            TransferMessage::Stake { proposal_id } => self.internal_stake(proposal_id, &sender_id, amount.0),
+            TransferMessage::Object { object_to } => self.internal_object(object_to, &sender_id, amount.0),
+        }
+        self.flush_collections();
+        self.charge_stake_storage(initial_storage_usage, &sender_id);
+        PromiseOrValue::Value(U128(0))
+    }
+
+    // Set NEAR aside to pay for the storage of future stakes, the first deposit also pays for its own entry
+    #[payable]
+    pub fn deposit_stake_storage(&mut self) -> NearToken {
+        let account_id = env::predecessor_account_id();
+        let initial_storage_usage = env::storage_usage();
+        let balance = self.get_stake_storage(account_id.clone()).saturating_add(env::attached_deposit());
+        self.stake_storage.insert(account_id.clone(), balance);
+        self.flush_collections();
+        let entry_cost = env::storage_byte_cost()
+            .saturating_mul(env::storage_usage().saturating_sub(initial_storage_usage) as u128);
+        assert!(balance >= entry_cost, "Attach at least {} to register for staking", entry_cost);
+        let balance = balance.saturating_sub(entry_cost);
+        self.stake_storage.insert(account_id, balance);
+        balance
+    }
+
+    // Take back the unused stake storage deposit, along with the storage of its entry
+    #[payable]
+    pub fn withdraw_stake_storage(&mut self) -> Promise {
+        assert!(env::attached_deposit() == ONE_YOCTO, "Requires attached deposit of exactly 1 yoctoNEAR");
+        let account_id = env::predecessor_account_id();
+        let initial_storage_usage = env::storage_usage();
+        let balance = self.stake_storage.remove(&account_id).expect("No stake storage deposit found");
+        self.flush_collections();
+        let freed = env::storage_byte_cost()
+            .saturating_mul(initial_storage_usage.saturating_sub(env::storage_usage()) as u128);
+        Promise::new(account_id).transfer(balance.saturating_add(freed))
+    }
+
+    // NEAR left to pay for the storage of new stakes
+    pub fn get_stake_storage(&self, account_id: AccountId) -> NearToken {
+        self.stake_storage.get(&account_id).copied().unwrap_or(NearToken::from_near(0))
+    }
+
+    // Take back a stake, all of it when `amount` is not set. Open proposals lose the conviction it was building.
+    // The attached yoctoNEAR is forwarded to `ft_transfer`.
+    #[payable]
+    pub fn withdraw_stake(&mut self, proposal_id: u64, amount: Option<U128>) -> Promise {
+        assert!(env::attached_deposit() == ONE_YOCTO, "Requires attached deposit of exactly 1 yoctoNEAR");
+        let account_id = env::predecessor_account_id();
+        let stake_key = (proposal_id, account_id.clone());
+        let stake = self.conviction_stakes.get(&stake_key).copied().expect("No stake found");
+        let amount = amount.map_or(stake, |amount| amount.0);
+        assert!(amount > 0 && amount <= stake, "Amount must be between 1 and the staked {}", stake);
+        self.accrue_conviction(proposal_id);
+        self.check_conviction(proposal_id);
+
+        // The storage of a stake entry taken back in full goes to the staker, who paid for it
+        let initial_storage_usage = env::storage_usage();
+        self.internal_unstake(proposal_id, &account_id, amount);
+        self.flush_collections();
+        Self::release_storage(initial_storage_usage, &account_id);
+
+        ft_contract::ext(self.token_contract_id.clone())
+            .with_attached_deposit(ONE_YOCTO)
+            .with_static_gas(self.gas_config.ft_transfer)
+            .with_unused_gas_weight(0)
+            .ft_transfer(account_id.clone(), U128(amount), None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(self.gas_config.withdraw_callback)
+                    .with_unused_gas_weight(1)
+                    .process_withdraw_callback(proposal_id, account_id, U128(amount)),
+            )
+    }
+
+    // Put the stake back if the transfer failed, without the conviction it lost meanwhile.
+    // The stake entry is paid again from the staker's stake storage deposit, as far as it goes.
+    #[private]
+    pub fn process_withdraw_callback(
+        &mut self,
+        proposal_id: u64,
+        account_id: AccountId,
+        amount: U128,
+        #[callback_result] result: Result<(), PromiseError>,
+    ) {
+        if let Err(e) = result {
+            env::log_str(&format!("Failed to return the stake of {}: {:?}", account_id, e));
+            let initial_storage_usage = env::storage_usage();
+            let stake_key = (proposal_id, account_id.clone());
+            let stake = self.conviction_stakes.get(&stake_key).copied().unwrap_or(0);
+            self.conviction_stakes.insert(stake_key, stake + amount.0);
+            let proposal = self.conviction_proposals.get_mut(&proposal_id).expect("Conviction proposal not found");
+            proposal.total_staked = U128(proposal.total_staked.0 + amount.0);
+            self.flush_collections();
+            self.recharge_stake_storage(initial_storage_usage, &account_id);
+        }
+    }
+
+    // Bring a proposal's conviction up to date and pass it if it crossed the threshold, anyone can call it
+    pub fn update_conviction(&mut self, proposal_id: u64) -> ProposalState {
+        self.assert_not_paused("update_conviction");
+        self.accrue_conviction(proposal_id);
+        self.check_conviction(proposal_id)
+    }
+
+    // Read the treasury size used in the thresholds from the configured treasury account
+    pub fn refresh_treasury(&mut self) -> Promise {
+        let treasury_account_id = self.conviction_config.treasury_account_id.clone().expect("No treasury account configured");
+        ft_contract::ext(self.token_contract_id.clone())
+            .with_static_gas(self.gas_config.ft_balance_of)
+            .with_unused_gas_weight(0)
+            .ft_balance_of(treasury_account_id)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(self.gas_config.vote_callback)
+                    .with_unused_gas_weight(1)
+                    .process_treasury_callback(),
+            )
+    }
+
+    #[private]
+    pub fn process_treasury_callback(&mut self, #[callback_result] balance: Result<U128, PromiseError>) -> U128 {
+        match balance {
+            Ok(balance) => {
+                env::log_str(&format!("Treasury balance updated to {}", balance.0));
+                self.treasury_balance = balance.0;
+                balance
+            }
+            Err(e) => env::panic_str(&format!("Failed to retrieve balance: {:?}", e)),
+        }
+    }
+
+    // Read the FDAO total supply the thresholds are based on, anyone can call it
+    pub fn refresh_token_supply(&mut self) -> Promise {
+        ft_contract::ext(self.token_contract_id.clone())
+            .with_static_gas(self.gas_config.ft_balance_of)
+            .with_unused_gas_weight(0)
+            .ft_total_supply()
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(self.gas_config.vote_callback)
+                    .with_unused_gas_weight(1)
+                    .process_token_supply_callback(),
+            )
+    }
+
+    #[private]
+    pub fn process_token_supply_callback(&mut self, #[callback_result] supply: Result<U128, PromiseError>) -> U128 {
+        match supply {
+            Ok(supply) => {
+                env::log_str(&format!("Token supply updated to {}", supply.0));
+                self.token_supply = supply.0;
+                supply
+            }
+            Err(e) => env::panic_str(&format!("Failed to retrieve total supply: {:?}", e)),
+        }
+    }
+
+    pub fn set_conviction_config(&mut self, conviction_config: ConvictionConfig) {
+        self.assert_governance();
+        conviction_config.assert_valid();
+        env::log_str(&format!("Conviction config updated to {:?}", conviction_config));
+        self.conviction_config = conviction_config;
+    }
+
+    pub fn get_conviction_config(&self) -> ConvictionConfig {
+        self.conviction_config.clone()
+    }
+
+    pub fn get_treasury_balance(&self) -> U128 {
+        U128(self.treasury_balance)
+    }
+
+    pub fn get_token_supply(&self) -> U128 {
+        U128(self.token_supply)
+    }
+
+    pub fn get_conviction_proposal(&self, proposal_id: u64) -> Option<ConvictionProposal> {
+        self.conviction_proposals.get(&proposal_id).cloned()
+    }
+
+    pub fn list_conviction_proposals(&self, from_id: Option<u64>, limit: Option<u64>) -> Vec<ConvictionProposal> {
+        (from_id.unwrap_or(0)..self.conviction_proposal_count)
+            .take(limit.unwrap_or(DEFAULT_PAGE_SIZE) as usize)
+            .filter_map(|proposal_id| self.conviction_proposals.get(&proposal_id).cloned())
+            .collect()
+    }
+
+    // Conviction as of the current period, frozen once the proposal passed
+    pub fn get_conviction(&self, proposal_id: u64) -> U128 {
+        let proposal = self.conviction_proposals.get(&proposal_id).expect("Conviction proposal not found");
+        if proposal.state != ProposalState::Open {
+            return proposal.conviction;
+        }
+        let periods = self.conviction_config.current_period().saturating_sub(proposal.last_period);
+        U128(self.conviction_config.accrue(proposal.conviction.0, proposal.total_staked.0, periods))
+    }
+
+    // Conviction the proposal needs to pass with the current treasury and token supply, None if it cannot pass
+    pub fn get_conviction_threshold(&self, proposal_id: u64) -> Option<U128> {
+        let proposal = self.conviction_proposals.get(&proposal_id).expect("Conviction proposal not found");
+        self.conviction_config
+            .threshold(proposal.requested_amount.0, self.treasury_balance, self.token_supply)
+            .map(U128)
+    }
+
+    pub fn get_conviction_stake(&self, proposal_id: u64, account_id: AccountId) -> U128 {
+        U128(self.conviction_stakes.get(&(proposal_id, account_id)).copied().unwrap_or(0))
+    }
+}
+
+impl ProposalContract {
+    fn internal_stake(&mut self, proposal_id: u64, staker: &AccountId, amount: u128) {
+        self.assert_not_paused("vote");
+        self.accrue_conviction(proposal_id);
+        let proposal = self.conviction_proposals.get_mut(&proposal_id).expect("Conviction proposal not found");
+        assert!(proposal.state == ProposalState::Open, "Conviction proposal is not open");
+        proposal.total_staked = U128(proposal.total_staked.0 + amount);
+        let stake_key = (proposal_id, staker.clone());
+        let stake = self.conviction_stakes.get(&stake_key).copied().unwrap_or(0);
+        self.conviction_stakes.insert(stake_key, stake + amount);
+        env::log_str(&format!("{} staked {} on conviction proposal {}", staker, amount, proposal_id));
+        self.check_conviction(proposal_id);
+    }
+
+    fn accrue_conviction(&mut self, proposal_id: u64) {
+        let current_period = self.conviction_config.current_period();
+        let proposal = self.conviction_proposals.get_mut(&proposal_id).expect("Conviction proposal not found");
+        if proposal.state != ProposalState::Open {
+            return;
+        }
+        let periods = current_period.saturating_sub(proposal.last_period);
+        proposal.conviction = U128(self.conviction_config.accrue(proposal.conviction.0, proposal.total_staked.0, periods));
+        proposal.last_period = current_period;
+    }
+
+    // Pass an open, staked proposal whose up to date conviction reached its threshold.
+    // A threshold rounded down to zero on a tiny supply never passes a proposal.
+    fn check_conviction(&mut self, proposal_id: u64) -> ProposalState {
+        let threshold = self.get_conviction_threshold(proposal_id);
+        let proposal = self.conviction_proposals.get_mut(&proposal_id).expect("Conviction proposal not found");
+        let reached = threshold.is_some_and(|threshold| threshold.0 > 0 && proposal.conviction.0 >= threshold.0);
+        if proposal.state == ProposalState::Open && proposal.total_staked.0 > 0 && reached {
+            proposal.state = ProposalState::Passed;
+            proposal.passed_at = Some(env::block_timestamp());
+            env::log_str(&format!(
+                "Conviction proposal {} passed, {} requested for {}",
+                proposal_id, proposal.requested_amount.0, proposal.beneficiary
+            ));
+        }
+        proposal.state.clone()
+    }
+
+    fn internal_unstake(&mut self, proposal_id: u64, account_id: &AccountId, amount: u128) {
+        let stake_key = (proposal_id, account_id.clone());
+        let stake = self.conviction_stakes.get(&stake_key).copied().unwrap_or(0);
+        if stake == amount {
+            self.conviction_stakes.remove(&stake_key);
+        } else {
+            self.conviction_stakes.insert(stake_key, stake - amount);
+        }
+        let proposal = self.conviction_proposals.get_mut(&proposal_id).expect("Conviction proposal not found");
+        proposal.total_staked = U128(proposal.total_staked.0 - amount);
+    }
+
+    // Pay the storage used since `initial_storage_usage` from the account's stake storage deposit
+    pub(crate) fn charge_stake_storage(&mut self, initial_storage_usage: u64, account_id: &AccountId) {
+        let cost = env::storage_byte_cost()
+            .saturating_mul(env::storage_usage().saturating_sub(initial_storage_usage) as u128);
+        if cost.is_zero() {
+            return;
+        }
+        let balance = self.get_stake_storage(account_id.clone());
+        assert!(balance >= cost, "Deposit at least {} with deposit_stake_storage before staking", cost);
+        self.stake_storage.insert(account_id.clone(), balance.saturating_sub(cost));
+    }
+
+    // Like `charge_stake_storage` for entries put back after a failed transfer, which cannot be
+    // turned down: the deposit pays as far as it goes
+    pub(crate) fn recharge_stake_storage(&mut self, initial_storage_usage: u64, account_id: &AccountId) {
+        let cost = env::storage_byte_cost()
+            .saturating_mul(env::storage_usage().saturating_sub(initial_storage_usage) as u128);
+        if let Some(balance) = self.stake_storage.get(account_id).copied() {
+            self.stake_storage.insert(account_id.clone(), balance.saturating_sub(cost));
+        }
+    }
+}
+
+// The rest of this file holds the inline tests for the code above
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::mock::MockAction;
+    use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    const PERIOD: u64 = 1_000;
+
+    fn set_context(predecessor: &str, period: u64) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("proposal".parse().unwrap())
+            .signer_account_id("alice".parse().unwrap())
+            .predecessor_account_id(predecessor.parse().unwrap())
+            .attached_deposit(NearToken::from_near(1))
+            .block_timestamp(period * PERIOD)
+            .build());
+    }
+
+    // Treasury of 1000 and supply of 100, 100 requested: the threshold is 1.25 times the supply
+    fn setup_contract() -> ProposalContract {
+        set_context("proposal", 0);
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        contract.set_conviction_config(ConvictionConfig { period: PERIOD, ..Default::default() });
+        contract.process_treasury_callback(Ok(U128(1_000)));
+        contract.process_token_supply_callback(Ok(U128(100)));
+        contract.create_conviction_proposal(
+            "Grant".to_string(),
+            "Fund the docs rewrite".to_string(),
+            "bob".parse().unwrap(),
+            U128(100),
+        );
+        set_context("alice", 0);
+        contract.deposit_stake_storage();
+        contract
+    }
+
+    fn stake(contract: &mut ProposalContract, account: &str, amount: u128) {
+        let message = serde_json::json!({ "proposal_id": 0 }).to_string();
+        contract.ft_on_transfer(account.parse().unwrap(), U128(amount), message);
+    }
+
+    #[test]
+    fn test_conviction_accrues_with_decay() {
+        let config = ConvictionConfig::default();
+        assert_eq!(config.accrue(0, 100, 1), 100);
+        assert_eq!(config.accrue(0, 100, 2), 180);
+        assert_eq!(config.accrue(180, 0, 1), 144);
+        // Approaches stake / (1 - decay)
+        assert_eq!(config.accrue(0, 100, 200), 500);
+    }
+
+    #[test]
+    fn test_threshold_grows_with_requested_share() {
+        let config = ConvictionConfig::default();
+        assert_eq!(config.threshold(100, 1_000, 100), Some(125));
+        assert_eq!(config.threshold(150, 1_000, 100), Some(500));
+        assert_eq!(config.threshold(200, 1_000, 100), None);
+        assert_eq!(config.threshold(100, 0, 100), None);
+    }
+
+    #[test]
+    fn test_proposal_passes_once_conviction_crosses_threshold() {
+        let mut contract = setup_contract();
+        set_context("token-contract", 0);
+        stake(&mut contract, "alice", 100);
+        assert_eq!(contract.get_conviction_threshold(0), Some(U128(125)));
+
+        set_context("carol", 1);
+        assert_eq!(contract.get_conviction(0), U128(100));
+        assert_eq!(contract.update_conviction(0), ProposalState::Open);
+
+        set_context("carol", 2);
+        assert_eq!(contract.get_conviction(0), U128(180));
+        assert_eq!(contract.update_conviction(0), ProposalState::Passed);
+        assert_eq!(contract.get_conviction_proposal(0).unwrap().passed_at, Some(2 * PERIOD));
+    }
+
+    #[test]
+    fn test_unstaked_proposal_does_not_pass() {
+        let mut contract = setup_contract();
+        assert_eq!(contract.get_conviction_threshold(0), Some(U128(125)));
+
+        set_context("carol", 5);
+        assert_eq!(contract.update_conviction(0), ProposalState::Open);
+    }
+
+    #[test]
+    fn test_dust_stake_does_not_pass() {
+        let mut contract = setup_contract();
+        contract.process_token_supply_callback(Ok(U128(1_000_000)));
+        set_context("token-contract", 0);
+        stake(&mut contract, "alice", 1);
+        assert_eq!(contract.get_conviction_threshold(0), Some(U128(1_250_000)));
+
+        set_context("carol", 1_000);
+        assert_eq!(contract.update_conviction(0), ProposalState::Open);
+        assert_eq!(contract.get_conviction(0), U128(5));
+    }
+
+    #[test]
+    fn test_threshold_needs_token_supply() {
+        set_context("proposal", 0);
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        contract.process_treasury_callback(Ok(U128(1_000)));
+        contract.create_conviction_proposal("Grant".to_string(), "Docs".to_string(), "bob".parse().unwrap(), U128(100));
+        assert_eq!(contract.get_conviction_threshold(0), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "with deposit_stake_storage before staking")]
+    fn test_stake_without_storage_deposit() {
+        let mut contract = setup_contract();
+        set_context("token-contract", 0);
+        stake(&mut contract, "carol", 100);
+    }
+
+    #[test]
+    fn test_stake_storage_is_paid_by_the_staker() {
+        let mut contract = setup_contract();
+        let deposit = contract.get_stake_storage("alice".parse().unwrap());
+        set_context("token-contract", 0);
+        stake(&mut contract, "alice", 100);
+        assert!(contract.get_stake_storage("alice".parse().unwrap()) < deposit);
+
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("proposal".parse().unwrap())
+            .predecessor_account_id("alice".parse().unwrap())
+            .attached_deposit(ONE_YOCTO)
+            .build());
+        contract.withdraw_stake_storage();
+        assert_eq!(contract.get_stake_storage("alice".parse().unwrap()), NearToken::from_near(0));
+        let refunds: Vec<NearToken> = get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                MockAction::Transfer { deposit, .. } => Some(deposit),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(refunds.len(), 1);
+        assert!(refunds[0] > deposit.saturating_sub(NearToken::from_millinear(10)));
+    }
+
+    #[test]
+    fn test_withdraw_stake_stops_accrual() {
+        let mut contract = setup_contract();
+        set_context("token-contract", 0);
+        stake(&mut contract, "alice", 100);
+
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("proposal".parse().unwrap())
+            .predecessor_account_id("alice".parse().unwrap())
+            .attached_deposit(ONE_YOCTO)
+            .block_timestamp(PERIOD)
+            .build());
+        contract.withdraw_stake(0, None);
+        assert_eq!(contract.get_conviction_stake(0, "alice".parse().unwrap()), U128(0));
+        // The freed stake entry storage goes back to the staker who paid for it
+        assert!(get_created_receipts()
+            .iter()
+            .filter(|receipt| receipt.receiver_id == "alice".parse::<AccountId>().unwrap())
+            .flat_map(|receipt| receipt.actions.iter())
+            .any(|action| matches!(action, MockAction::Transfer { .. })));
+        set_context("carol", 2);
+        assert_eq!(contract.get_conviction(0), U128(80));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the voting token can be staked")]
+    fn test_stake_other_token() {
+        let mut contract = setup_contract();
+        set_context("other-token", 0);
+        stake(&mut contract, "alice", 100);
+    }
+}
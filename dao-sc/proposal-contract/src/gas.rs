@@ -0,0 +1,27 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::Gas;
+use serde::{Deserialize, Serialize};
+
+// Static gas reserved for each cross-contract call, prepaid gas left over is forwarded on top
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct GasConfig {
+    // Also reserved for the NFT and membership queries of the other voting power sources
+    pub ft_balance_of: Gas,
+    pub vote_callback: Gas,
+    pub comment_callback: Gas,
+    // Returning withdrawn conviction stakes
+    pub ft_transfer: Gas,
+    pub withdraw_callback: Gas,
+}
+
+impl Default for GasConfig {
+    fn default() -> Self {
+        Self {
+            ft_balance_of: Gas::from_tgas(5),
+            vote_callback: Gas::from_tgas(10),
+            comment_callback: Gas::from_tgas(10),
+            ft_transfer: Gas::from_tgas(10),
+            withdraw_callback: Gas::from_tgas(10),
+        }
+    }
+}
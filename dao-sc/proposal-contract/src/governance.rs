@@ -0,0 +1,102 @@
+use near_sdk::{env, near_bindgen, AccountId};
+use crate::ProposalContract;
+use crate::ProposalContractExt;
+
+#[near_bindgen]
+impl ProposalContract {
+    // Hand the contract settings to another account, usually the DAO that created the contract.
+    // The contract account itself always keeps them.
+    pub fn set_governance(&mut self, governance_id: Option<AccountId>) {
+        self.assert_governance();
+        env::log_str(&format!("Governance set to {:?}", governance_id));
+        self.governance_id = governance_id;
+    }
+
+    pub fn get_governance(&self) -> Option<AccountId> {
+        self.governance_id.clone()
+    }
+}
+
+impl ProposalContract {
+    pub(crate) fn is_governance(&self, account_id: &AccountId) -> bool {
+        account_id == &env::current_account_id() || self.governance_id.as_ref() == Some(account_id)
+    }
+
+    // Settings, moderators, categories and unpausing are reserved to the governance account
+    pub(crate) fn assert_governance(&self) {
+        assert!(self.is_governance(&env::predecessor_account_id()), "Only governance can call this method");
+    }
+}
+
+// The rest of this file holds the inline tests for the code above
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GasConfig, OptimisticConfig, ProposalLimits, ReputationConfig, VotingPowerSource};
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    fn set_caller(account: &str) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("proposal".parse().unwrap())
+            .predecessor_account_id(account.parse().unwrap())
+            .build());
+    }
+
+    fn setup_contract() -> ProposalContract {
+        set_caller("proposal");
+        ProposalContract::new("token-contract".parse().unwrap(), Some("dao".parse().unwrap()))
+    }
+
+    #[test]
+    fn test_governance_updates_settings() {
+        let mut contract = setup_contract();
+        set_caller("dao");
+        contract.set_limits(ProposalLimits { max_options: 3, ..Default::default() });
+        contract.add_category("grants".to_string());
+        contract.add_moderator("carol".parse().unwrap());
+        contract.set_voting_power_source(VotingPowerSource::NftCount { nft_contract_id: "nft".parse().unwrap() });
+        assert_eq!(contract.get_limits().max_options, 3);
+        assert_eq!(contract.get_categories(), vec!["grants".to_string()]);
+        assert_eq!(contract.get_moderators(), vec!["carol".parse::<AccountId>().unwrap()]);
+
+        // Governance can be handed over, the contract account keeps it either way
+        contract.set_governance(Some("new-dao".parse().unwrap()));
+        set_caller("proposal");
+        contract.set_reputation_config(ReputationConfig::default());
+        assert_eq!(contract.get_governance(), Some("new-dao".parse().unwrap()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only governance can call this method")]
+    fn test_set_gas_config_not_governance() {
+        let mut contract = setup_contract();
+        set_caller("mallory");
+        contract.set_gas_config(GasConfig::default());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only governance can call this method")]
+    fn test_add_moderator_not_governance() {
+        let mut contract = setup_contract();
+        set_caller("mallory");
+        contract.add_moderator("mallory".parse().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only governance can call this method")]
+    fn test_set_optimistic_config_not_governance() {
+        let mut contract = setup_contract();
+        set_caller("mallory");
+        contract.set_optimistic_config(OptimisticConfig::default());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only governance can call this method")]
+    fn test_set_governance_not_governance() {
+        let mut contract = setup_contract();
+        set_caller("mallory");
+        contract.set_governance(Some("mallory".parse().unwrap()));
+    }
+}
@@ -0,0 +1,160 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{env, near_bindgen, AccountId};
+use serde::{Deserialize, Serialize};
+use crate::ProposalContract;
+use crate::ProposalContractExt;
+use crate::vote::Ballot;
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+
+// A ballot in an account's voting history
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct VoteRecord {
+    pub proposal_id: u64,
+    pub ballot: Ballot,
+    pub weight: u64,
+    pub voted_at: u64,
+}
+
+// Per-account counters kept alongside the history
+#[derive(BorshDeserialize, BorshSerialize, PartialEq, Debug, Clone)]
+pub struct VoterStats {
+    // Lowest proposal id the account voted on, the account counts as joined from there
+    first_proposal_id: u64,
+    proposals_voted: u64,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct ParticipationStats {
+    pub proposals_voted: u64,
+    // Proposals created since the account joined, including the first one it voted on
+    pub eligible_proposals: u64,
+    pub participation_percent: u8,
+}
+
+#[near_bindgen]
+impl ProposalContract {
+    // Get the ballots of an account ordered by proposal id, starting at `from`
+    pub fn get_votes_by_account(&self, account: AccountId, from: Option<u64>, limit: Option<u64>) -> Vec<VoteRecord> {
+        let start = (account.clone(), from.unwrap_or(0));
+        let end = (account, u64::MAX);
+        self.votes_by_account
+            .range(start..=end)
+            .take(limit.unwrap_or(DEFAULT_PAGE_SIZE) as usize)
+            .map(|(_key, record)| record.clone())
+            .collect()
+    }
+
+    pub fn get_participation_stats(&self, account: AccountId) -> ParticipationStats {
+        match self.voter_stats.get(&account) {
+            Some(stats) => {
+                let eligible_proposals = self.proposal_count.saturating_sub(stats.first_proposal_id);
+                let participation_percent = (stats.proposals_voted * 100)
+                    .checked_div(eligible_proposals)
+                    .unwrap_or(0)
+                    .min(100) as u8;
+                ParticipationStats {
+                    proposals_voted: stats.proposals_voted,
+                    eligible_proposals,
+                    participation_percent,
+                }
+            }
+            None => ParticipationStats {
+                proposals_voted: 0,
+                eligible_proposals: 0,
+                participation_percent: 0,
+            },
+        }
+    }
+}
+
+impl ProposalContract {
+    pub(crate) fn record_vote(&mut self, voter: &AccountId, proposal_id: u64, ballot: Ballot, weight: u64) {
+        let record = VoteRecord {
+            proposal_id,
+            ballot,
+            weight,
+            voted_at: env::block_timestamp(),
+        };
+        self.votes_by_account.insert((voter.clone(), proposal_id), record);
+        let stats = self.voter_stats.entry(voter.clone()).or_insert(VoterStats {
+            first_proposal_id: proposal_id,
+            proposals_voted: 0,
+        });
+        stats.first_proposal_id = stats.first_proposal_id.min(proposal_id);
+        stats.proposals_voted += 1;
+    }
+}
+
+// The rest of this file holds the inline tests for the code above
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{testing_env, NearToken};
+
+    fn get_context() -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id("proposal".parse().unwrap())
+            .signer_account_id("alice".parse().unwrap())
+            .predecessor_account_id("alice".parse().unwrap())
+            .account_balance(NearToken::from_near(10))
+            .attached_deposit(NearToken::from_near(1))
+            .block_timestamp(0);
+        builder
+    }
+
+    fn setup_contract(proposals: u64) -> ProposalContract {
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        for _ in 0..proposals {
+            contract.create_proposal(
+                "title".to_string(),
+                "description".to_string(),
+                1000,
+                vec!["Yes".to_string(), "No".to_string()],
+                1,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            );
+        }
+        contract
+    }
+
+    #[test]
+    fn test_get_votes_by_account() {
+        testing_env!(get_context().build());
+        let mut contract = setup_contract(3);
+        contract.process_vote_callback(0, "alice".parse().unwrap(), Ballot::Option(1), Ok(U128(1)));
+        contract.process_vote_callback(2, "alice".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
+        contract.process_vote_callback(1, "bob".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
+
+        let history = contract.get_votes_by_account("alice".parse().unwrap(), None, None);
+        assert_eq!(history.iter().map(|record| (record.proposal_id, record.ballot.clone())).collect::<Vec<_>>(),
+            vec![(0, Ballot::Option(1)), (2, Ballot::Option(0))]);
+        assert_eq!(history[0].weight, 1);
+        let page = contract.get_votes_by_account("alice".parse().unwrap(), Some(1), Some(1));
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].proposal_id, 2);
+    }
+
+    #[test]
+    fn test_participation_since_first_vote() {
+        testing_env!(get_context().build());
+        let mut contract = setup_contract(4);
+        contract.process_vote_callback(1, "alice".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
+        contract.process_vote_callback(3, "alice".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
+
+        let stats = contract.get_participation_stats("alice".parse().unwrap());
+        assert_eq!(stats.proposals_voted, 2);
+        assert_eq!(stats.eligible_proposals, 3);
+        assert_eq!(stats.participation_percent, 66);
+        assert_eq!(contract.get_participation_stats("bob".parse().unwrap()).proposals_voted, 0);
+    }
+}
@@ -1,9 +1,65 @@
+// The cross-contract wrapper near_bindgen generates for `create_proposal` takes every argument
+#![allow(clippy::too_many_arguments)]
+
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{UnorderedMap, Vector};
-use near_sdk::{ env, near_bindgen, AccountId, PanicOnDefault};
+use near_sdk::json_types::{Base64VecU8, U128};
+use near_sdk::store::{IterableMap, IterableSet, LookupMap, TreeMap};
+use near_sdk::{ env, near_bindgen, AccountId, BorshStorageKey, NearToken, PanicOnDefault, Promise};
 use serde::{Serialize, Deserialize};
 
+mod archive;
+mod categories;
+mod comments;
+mod commit_reveal;
+mod conviction;
+mod gas;
+mod governance;
+mod history;
+mod limits;
+mod optimistic;
+mod pause;
+mod reputation;
+mod storage;
 mod vote;
+mod voting_power;
+
+pub use archive::ArchivedProposal;
+pub use categories::ProposalSummary;
+pub use comments::Comment;
+pub use conviction::{ConvictionConfig, ConvictionProposal};
+pub use gas::GasConfig;
+pub use history::{ParticipationStats, VoteRecord};
+pub use limits::ProposalLimits;
+pub use optimistic::OptimisticConfig;
+pub use reputation::ReputationConfig;
+pub use vote::{Ballot, BallotCounts, VetoConfig};
+pub use voting_power::{VotingPowerSource, VotingWeight, WeightedSource};
+
+// Storage prefixes of the contract collections
+#[derive(BorshSerialize, BorshStorageKey)]
+#[borsh(crate = "near_sdk::borsh")]
+enum StorageKey {
+    Proposals,
+    Votes,
+    Voters,
+    Archived,
+    OpenByDeadline,
+    Comments,
+    Moderators,
+    Categories,
+    ProposalsByCategory,
+    ProposalsByTag,
+    VotesByAccount,
+    VoterStats,
+    Commitments,
+    Committers,
+    Reputation,
+    ConvictionProposals,
+    ConvictionStakes,
+    StakeStorage,
+    Objections,
+    Objectors,
+}
 
 // Represent the state of a proposal
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -14,73 +70,304 @@ pub enum ProposalState {
     Rejected,
 }
 
-impl ToString for ProposalState {
-    fn to_string(&self) -> String {
+// How ballots are cast on a proposal
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub enum VotingMode {
+    // Ballots are counted as they are cast
+    #[default]
+    Open,
+    // Voters commit to sha256(option || salt) until `commit_deadline`, then reveal until the deadline
+    CommitReveal { commit_deadline: u64 },
+    // Passes at the deadline unless objections backed by locked FDAO escalate it to a regular vote.
+    // Option 0 approves the proposal, an escalated vote passes only if it wins.
+    Optimistic,
+}
+
+impl std::fmt::Display for ProposalState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ProposalState::Open => "Open".to_owned(),
-            ProposalState::Closed => "Closed".to_owned(),
-            ProposalState::Passed => "Passed".to_owned(),
-            ProposalState::Rejected => "Rejected".to_owned(),
+            ProposalState::Open => write!(f, "Open"),
+            ProposalState::Closed => write!(f, "Closed"),
+            ProposalState::Passed => write!(f, "Passed"),
+            ProposalState::Rejected => write!(f, "Rejected"),
         }
     }
 }
 
-//Proposal Structure
+//Proposal Structure, ballots live in the contract level `votes` map
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Proposal {
     title: String, 
+    // Short summary when the full text is linked off-chain
     description: String,
+    // Where the full text can be fetched, e.g. an IPFS CID or a URL
+    link: Option<String>,
+    // sha256 of the full text
+    content_hash: Option<Base64VecU8>,
+    category: Option<String>,
+    tags: Vec<String>,
     deadline: u64,
-    options: Vector<String>,
+    options: Vec<String>,
     minimum_votes: u8,
-    votes: UnorderedMap<AccountId, u8>,
+    // Running vote weight per option, one per ballot unless the proposal is token weighted
+    tally: Vec<u64>,
+    abstain_count: u64,
+    veto_count: u64,
+    voting_weight: VotingWeight,
+    // Every ballot cast, options, abstentions and vetoes alike
+    voter_count: u64,
+    comment_count: u64,
+    voting_mode: VotingMode,
+    // Commitments submitted in commit-reveal mode, revealed ones are also counted in `voter_count`
+    commit_count: u64,
+    // FDAO behind the objections to an optimistic proposal, and when they escalated it
+    objection_weight: U128,
+    objection_count: u64,
+    escalated_at: Option<u64>,
+    // Ballots, commitments, objections and comments already cleared by `archive_proposal`
+    archive_cursor: u64,
     state: ProposalState,
+    // Account that paid the storage deposit for the proposal
+    storage_payer: AccountId,
+    // Held until finalization, returned to the proposer unless the proposal is vetoed
+    bond: NearToken,
+    // Only proposals created through the governance account earn reputation,
+    // accounts cannot farm it with proposals they create themselves
+    earns_reputation: bool,
+}
+
+impl Proposal {
+    // Once `minimum_votes` ballots of any kind are cast, the option with the most votes wins.
+    // Abstentions only count toward that quorum, ties have no winner
+    pub(crate) fn winning_option(&self) -> Option<u8> {
+        if self.voter_count < self.minimum_votes as u64 {
+            return None;
+        }
+        let top_count = self.tally.iter().copied().max().unwrap_or(0);
+        let mut leaders = self.tally.iter().enumerate().filter(|(_index, count)| **count == top_count);
+        match (leaders.next(), leaders.next()) {
+            (Some((index, _count)), None) if top_count > 0 => Some(index as u8),
+            _ => None,
+        }
+    }
+
+    // Vetoes are weighed against the total weight cast, which is the ballot count on unweighted proposals
+    pub(crate) fn is_vetoed(&self, threshold_percent: u8) -> bool {
+        let total_weight = self.tally.iter().sum::<u64>() + self.abstain_count + self.veto_count;
+        self.veto_count > 0 && self.veto_count as u128 * 100 > total_weight as u128 * threshold_percent as u128
+    }
+}
+
+// Off-chain content reference returned by `get_proposal_content`
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct ProposalContent {
+    pub summary: String,
+    pub link: Option<String>,
+    pub content_hash: Option<Base64VecU8>,
 }
 
 //Proposal Contract Structure
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct ProposalContract {
-    proposals: UnorderedMap<u64, Proposal>,
+    proposals: IterableMap<u64, Proposal>,
+    // Ballot cast by each account, keyed by (proposal_id, account)
+    votes: LookupMap<(u64, AccountId), Ballot>,
+    // (voter, storage payer) of each ballot in ballot order, keyed by (proposal_id, index)
+    voters: LookupMap<(u64, u64), (AccountId, AccountId)>,
     proposal_count: u64,
     token_contract_id: AccountId,
+    limits: ProposalLimits,
+    gas_config: GasConfig,
+    veto_config: VetoConfig,
+    archived: LookupMap<u64, ArchivedProposal>,
+    // Open proposals ordered by (deadline, proposal_id)
+    open_by_deadline: TreeMap<(u64, u64), ()>,
+    // Comments of each proposal in posting order, keyed by (proposal_id, comment_id)
+    comments: LookupMap<(u64, u64), Comment>,
+    moderators: IterableSet<AccountId>,
+    // Categories new proposals can use
+    categories: IterableSet<String>,
+    // Proposal ids per category and per tag, keyed by (category or tag, proposal_id)
+    proposals_by_category: TreeMap<(String, u64), ()>,
+    proposals_by_tag: TreeMap<(String, u64), ()>,
+    // Voting history of each account, keyed by (account, proposal_id), cleared when the proposal is archived
+    votes_by_account: TreeMap<(AccountId, u64), VoteRecord>,
+    voter_stats: LookupMap<AccountId, history::VoterStats>,
+    // Commit-reveal commitments keyed by (proposal_id, account)
+    commitments: LookupMap<(u64, AccountId), Base64VecU8>,
+    // Committers of each proposal in commit order, keyed by (proposal_id, index)
+    committers: LookupMap<(u64, u64), AccountId>,
+    // Can pause methods in an emergency, only the contract account unpauses them
+    guardian: Option<AccountId>,
+    paused_methods: Vec<String>,
+    // Token balance by default, membership for working groups without a token
+    voting_power_source: VotingPowerSource,
+    reputation_config: ReputationConfig,
+    reputation: LookupMap<AccountId, reputation::ReputationEntry>,
+    // Conviction voting proposals have their own ids
+    conviction_proposals: LookupMap<u64, ConvictionProposal>,
+    conviction_proposal_count: u64,
+    // FDAO staked per (proposal_id, account), held by this contract
+    conviction_stakes: LookupMap<(u64, AccountId), u128>,
+    // NEAR each account set aside to store its stakes, see `deposit_stake_storage`
+    stake_storage: LookupMap<AccountId, NearToken>,
+    conviction_config: ConvictionConfig,
+    // Last treasury balance read by `refresh_treasury`
+    treasury_balance: u128,
+    // Last FDAO total supply read by `refresh_token_supply`
+    token_supply: u128,
+    // FDAO locked by each objector until the proposal is finalized, keyed by (proposal_id, account)
+    objections: LookupMap<(u64, AccountId), U128>,
+    // Objectors of each proposal in objection order, keyed by (proposal_id, index)
+    objectors: LookupMap<(u64, u64), AccountId>,
+    optimistic_config: OptimisticConfig,
+    // Manages the settings along with the contract account, usually the DAO
+    governance_id: Option<AccountId>,
 }
 
 // Implement the Proposal Contract
 #[near_bindgen]
 impl ProposalContract {
-    //Iinitializes the contract
+    //Iinitializes the contract, `governance_id` is the account besides this one that manages the settings
     #[init]
-    pub fn new(token_contract_id: AccountId) -> Self {
+    pub fn new(token_contract_id: AccountId, governance_id: Option<AccountId>) -> Self {
         //assert!(!env::state_exists(), "The contract is already initialized");
         Self {
-            proposals: UnorderedMap::new(b"p"),
+            proposals: IterableMap::new(StorageKey::Proposals),
+            votes: LookupMap::new(StorageKey::Votes),
+            voters: LookupMap::new(StorageKey::Voters),
             proposal_count: 0,
             token_contract_id,
+            limits: ProposalLimits::default(),
+            gas_config: GasConfig::default(),
+            veto_config: VetoConfig::default(),
+            archived: LookupMap::new(StorageKey::Archived),
+            open_by_deadline: TreeMap::new(StorageKey::OpenByDeadline),
+            comments: LookupMap::new(StorageKey::Comments),
+            moderators: IterableSet::new(StorageKey::Moderators),
+            categories: IterableSet::new(StorageKey::Categories),
+            proposals_by_category: TreeMap::new(StorageKey::ProposalsByCategory),
+            proposals_by_tag: TreeMap::new(StorageKey::ProposalsByTag),
+            votes_by_account: TreeMap::new(StorageKey::VotesByAccount),
+            voter_stats: LookupMap::new(StorageKey::VoterStats),
+            commitments: LookupMap::new(StorageKey::Commitments),
+            committers: LookupMap::new(StorageKey::Committers),
+            guardian: None,
+            paused_methods: Vec::new(),
+            voting_power_source: VotingPowerSource::default(),
+            reputation_config: ReputationConfig::default(),
+            reputation: LookupMap::new(StorageKey::Reputation),
+            conviction_proposals: LookupMap::new(StorageKey::ConvictionProposals),
+            conviction_proposal_count: 0,
+            conviction_stakes: LookupMap::new(StorageKey::ConvictionStakes),
+            stake_storage: LookupMap::new(StorageKey::StakeStorage),
+            conviction_config: ConvictionConfig::default(),
+            treasury_balance: 0,
+            token_supply: 0,
+            objections: LookupMap::new(StorageKey::Objections),
+            objectors: LookupMap::new(StorageKey::Objectors),
+            optimistic_config: OptimisticConfig::default(),
+            governance_id,
         }
     }
 
-    // Create a new proposal
-    pub fn create_proposal(&mut self, title: String, description: String, deadline: u64, options_vec: Vec<String>, minimum_votes: u8) -> u64 {
+    // Update the bounds enforced on new proposals
+    pub fn set_limits(&mut self, limits: ProposalLimits) {
+        self.assert_governance();
+        env::log_str(&format!("Proposal limits updated to {:?}", limits));
+        self.limits = limits;
+    }
+
+    pub fn get_limits(&self) -> ProposalLimits {
+        self.limits.clone()
+    }
+
+    // Update the static gas reserved for cross-contract calls
+    pub fn set_gas_config(&mut self, gas_config: GasConfig) {
+        self.assert_governance();
+        env::log_str(&format!("Gas config updated to {:?}", gas_config));
+        self.gas_config = gas_config;
+    }
+
+    pub fn get_gas_config(&self) -> GasConfig {
+        self.gas_config.clone()
+    }
+
+    // Create a new proposal, the attached deposit must cover its storage
+    #[payable]
+    pub fn create_proposal(
+        &mut self,
+        title: String,
+        description: String,
+        deadline: u64,
+        options_vec: Vec<String>,
+        minimum_votes: u8,
+        link: Option<String>,
+        content_hash: Option<Base64VecU8>,
+        category: Option<String>,
+        tags: Option<Vec<String>>,
+        voting_mode: Option<VotingMode>,
+        voting_weight: Option<VotingWeight>,
+    ) -> u64 {
+        self.assert_not_paused("create_proposal");
         env::log_str(&format!("Received proposal with title: {}, deadline: {}", title, deadline));
-        let proposal_id = self.proposal_count;
-        let mut options = Vector::new(b"p");
-        for option in options_vec.into_iter() {
-            options.push(&option);
+        let initial_storage_usage = env::storage_usage();
+        let storage_payer = env::signer_account_id();
+        self.limits.assert_valid(&title, &description, deadline, &options_vec, env::block_timestamp());
+        self.limits.assert_valid_content(&description, link.as_deref(), content_hash.as_ref().map(|hash| hash.0.as_slice()));
+        let tags = tags.unwrap_or_default();
+        self.limits.assert_valid_tags(&tags);
+        self.assert_valid_category(category.as_ref());
+        let voting_mode = voting_mode.unwrap_or_default();
+        if let VotingMode::CommitReveal { commit_deadline } = voting_mode {
+            assert!(
+                commit_deadline > env::block_timestamp() && commit_deadline < deadline,
+                "Commit deadline must be between now and the proposal deadline"
+            );
         }
+        let voting_weight = voting_weight.unwrap_or_default();
+        voting_weight.assert_valid();
+        assert!(
+            !matches!(voting_mode, VotingMode::CommitReveal { .. }) || voting_weight == VotingWeight::OnePerVoter,
+            "Weighted voting is not available with commit-reveal voting"
+        );
+        let proposal_id = self.proposal_count;
         assert!(deadline > env::block_timestamp(), "Deadline must be in the future");
-        assert!(options.len() > 1, "At least two options are required");
+        assert!(options_vec.len() > 1, "At least two options are required");
         let new_proposal = Proposal {
             title: title.clone(),
             description,
+            link,
+            content_hash,
+            category: category.clone(),
+            tags: tags.clone(),
             deadline,
-            options,
+            tally: vec![0; options_vec.len()],
+            abstain_count: 0,
+            veto_count: 0,
+            voting_weight,
+            options: options_vec,
             minimum_votes,
-            votes: UnorderedMap::new(format!("v{}", proposal_id).as_bytes()),
+            voter_count: 0,
+            comment_count: 0,
+            voting_mode,
+            commit_count: 0,
+            objection_weight: U128(0),
+            objection_count: 0,
+            escalated_at: None,
+            archive_cursor: 0,
             state: ProposalState::Open,
+            storage_payer: storage_payer.clone(),
+            bond: self.veto_config.proposal_bond,
+            earns_reputation: self.is_governance(&env::predecessor_account_id()),
         };
-        self.proposals.insert(&proposal_id, &new_proposal);
+        self.proposals.insert(proposal_id, new_proposal);
+        self.open_by_deadline.insert((deadline, proposal_id), ());
+        self.index_proposal(proposal_id, category.as_ref(), &tags);
+        self.flush_collections();
         self.proposal_count += 1;
+        Self::charge_storage_and_bond(initial_storage_usage, &storage_payer, self.veto_config.proposal_bond);
         env::log_str(&format!("Proposal {} created: '{}'", proposal_id, title));
         proposal_id
     }
@@ -93,12 +380,30 @@ impl ProposalContract {
                 proposal.description.clone(),
                 proposal.deadline.to_string(),
                 proposal.minimum_votes.to_string(),
-                proposal.options.to_vec().join(","),
+                proposal.options.join(","),
                 proposal.state.to_string(),  
             ]
         })
     }
 
+    // Get the summary and the off-chain content reference of a proposal
+    pub fn get_proposal_content(&self, proposal_id: u64) -> Option<ProposalContent> {
+        self.proposals.get(&proposal_id).map(|proposal| ProposalContent {
+            summary: proposal.description.clone(),
+            link: proposal.link.clone(),
+            content_hash: proposal.content_hash.clone(),
+        })
+    }
+
+    // Check fetched content against the stored hash, `false` when the proposal has no hash
+    pub fn verify_content(&self, proposal_id: u64, content: String) -> bool {
+        let proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        match &proposal.content_hash {
+            Some(content_hash) => env::sha256(content.as_bytes()) == content_hash.0,
+            None => false,
+        }
+    }
+
     // List all proposals
     pub fn list_proposals(&self) -> Vec<Vec<String>> {
         self.proposals
@@ -113,15 +418,68 @@ impl ProposalContract {
             .collect()
     }
 
-    // Update proposal status
-    pub fn update_status(&mut self, proposal_id: u64) {
-        let mut proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+    // Update proposal status and return the final state
+    pub fn update_status(&mut self, proposal_id: u64) -> ProposalState {
+        self.assert_not_paused("update_status");
+        let proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        // A finalized proposal keeps its state, its bond and rewards were settled the first time
+        if proposal.state != ProposalState::Open {
+            return proposal.state.clone();
+        }
+        let new_state = self.count_votes(proposal_id);
+        let threshold_percent = self.veto_config.threshold_percent;
+        let proposal = self.proposals.get_mut(&proposal_id).expect("Proposal not found");
         assert!(env::block_timestamp() > proposal.deadline, "Proposal deadline has not passed yet");
-        // let new_state = self.count_votes(proposal_id);
-        let new_state = ProposalState::Open;
+        let newly_passed = new_state == ProposalState::Passed;
         proposal.state = new_state.clone();
-        self.proposals.insert(&proposal_id, &proposal);
+        let bond = std::mem::replace(&mut proposal.bond, NearToken::from_near(0));
+        if proposal.is_vetoed(threshold_percent) {
+            // The slashed bond stays with the contract
+            env::log_str(&format!("Proposal {} vetoed, bond of {} slashed", proposal_id, bond));
+        } else if !bond.is_zero() {
+            Promise::new(proposal.storage_payer.clone()).transfer(bond);
+        }
+        let deadline = proposal.deadline;
+        // Commitments that were never revealed are left out of the tallies
+        let unrevealed = proposal.commit_count.saturating_sub(proposal.voter_count);
+        if unrevealed > 0 {
+            env::log_str(&format!("{} unrevealed commitments discarded", unrevealed));
+        }
+        if proposal.voting_mode == VotingMode::Optimistic && proposal.escalated_at.is_none() {
+            env::log_str(&format!("Proposal {} passed without enough objections to escalate", proposal_id));
+        }
+        let proposer = proposal.storage_payer.clone();
+        let earns_reputation = proposal.earns_reputation;
+        self.open_by_deadline.remove(&(deadline, proposal_id));
+        if newly_passed && earns_reputation {
+            self.award_reputation(&proposer, self.reputation_config.passed_proposal_reward);
+        }
         env::log_str(&format!("Proposal {} status updated to {:?}", proposal_id, new_state));
+        new_state
+    }
+
+    // Finalize up to `limit` open proposals whose deadline has passed, oldest deadline first.
+    // Anyone can call it, returns how many proposals were finalized.
+    pub fn finalize_expired(&mut self, limit: u32) -> u32 {
+        self.assert_not_paused("finalize_expired");
+        let now = env::block_timestamp();
+        let expired: Vec<u64> = self
+            .open_by_deadline
+            .keys()
+            .take_while(|(deadline, _proposal_id)| *deadline < now)
+            .take(limit as usize)
+            .map(|(_deadline, proposal_id)| *proposal_id)
+            .collect();
+        for proposal_id in expired.iter() {
+            self.update_status(*proposal_id);
+        }
+        env::log_str(&format!("Finalized {} expired proposals", expired.len()));
+        expired.len() as u32
+    }
+
+    // Number of proposals still waiting to be finalized
+    pub fn get_open_proposal_count(&self) -> u32 {
+        self.open_by_deadline.len()
     }
 }
 
@@ -140,7 +498,7 @@ mod tests {
     fn test_proposal() {
         let context = get_context("alice".parse().unwrap());
         testing_env!(context);
-        let mut contract = ProposalContract::new("token-contract".parse().unwrap());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
         let options_vec = vec!["Yes".to_string(), "No".to_string()];
         let proposal_id = contract.create_proposal(
             "Test Proposal".to_string(),
@@ -148,13 +506,17 @@ mod tests {
             env::block_timestamp() + 1000,
             options_vec,
             1,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
         let proposal = contract.get_proposal(proposal_id).expect("Proposal not found");
-        assert_eq!(proposal.title, "Test Proposal".to_string());
-        assert_eq!(proposal.state, ProposalState::Open);
-        assert_eq!(proposal.options.len(), 2);
-        assert_eq!(proposal.options.get(0).unwrap(), "Yes".to_string());
-        assert_eq!(proposal.options.get(1).unwrap(), "No".to_string());
+        assert_eq!(proposal[0], "Test Proposal".to_string());
+        assert_eq!(proposal[5], ProposalState::Open.to_string());
+        assert_eq!(proposal[4], "Yes,No".to_string());
     }
 
     fn get_context(predecessor_account_id: AccountId) -> VMContext {
@@ -167,10 +529,10 @@ mod tests {
             input: vec![],
             block_index: 0,
             block_timestamp: 1_600_000_000_000,
-            account_balance: NearToken::from_yoctonear(5),
+            account_balance: NearToken::from_near(10),
             account_locked_balance: NearToken::from_yoctonear(0),
             storage_usage: 10u64.pow(6),
-            attached_deposit: NearToken::from_yoctonear(0),
+            attached_deposit: NearToken::from_near(1),
             prepaid_gas: Gas::from_tgas(200),
             random_seed: [0u8; 32],
             view_config: None,
@@ -183,7 +545,7 @@ mod tests {
     fn test_update_status() {
         let context = get_context("alice".parse().unwrap());
         testing_env!(context);
-        let mut contract = ProposalContract::new("token-contract".parse().unwrap());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
         let options_vec = vec!["Yes".to_string(), "No".to_string()];
         let proposal_id = contract.create_proposal(
             "Test Proposal".to_string(),
@@ -202,7 +564,7 @@ mod tests {
     fn test_list_proposals() {
         let context = get_context("alice".parse().unwrap());
         testing_env!(context);
-        let mut contract = ProposalContract::new("token-contract".parse().unwrap());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
         let options_vec = vec!["Yes".to_string(), "No".to_string()];
         let proposal_id = contract.create_proposal(
             "Test Proposal".to_string(),
@@ -210,10 +572,111 @@ mod tests {
             env::block_timestamp() + 1000,
             options_vec,
             1,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
         let proposals = contract.list_proposals();
         assert_eq!(proposals.len(), 1);
-        assert_eq!(proposals[0].0, proposal_id);
+        assert_eq!(proposals[0][0], proposal_id.to_string());
+    }
+
+    #[test]
+    fn test_proposals_keep_their_own_options() {
+        let context = get_context("alice".parse().unwrap());
+        testing_env!(context);
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        let all_options = [
+            vec!["Yes".to_string(), "No".to_string()],
+            vec!["Red".to_string(), "Green".to_string(), "Blue".to_string()],
+            vec!["Now".to_string(), "Later".to_string()],
+        ];
+        for (index, options_vec) in all_options.iter().enumerate() {
+            contract.create_proposal(
+                format!("Proposal {}", index),
+                "This is a test proposal".to_string(),
+                env::block_timestamp() + 1000,
+                options_vec.clone(),
+                1,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            );
+        }
+        for (proposal_id, options_vec) in all_options.iter().enumerate() {
+            let proposal = contract.get_proposal(proposal_id as u64).expect("Proposal not found");
+            assert_eq!(proposal[4], options_vec.join(","));
+        }
+    }
+
+    #[test]
+    fn test_finalize_expired() {
+        let mut context = get_context("alice".parse().unwrap());
+        testing_env!(context.clone());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        let now = env::block_timestamp();
+        // Created out of deadline order
+        for offset in [3000, 1000, 2000] {
+            contract.create_proposal(
+                format!("Proposal {}", offset),
+                "This is a test proposal".to_string(),
+                now + offset,
+                vec!["Yes".to_string(), "No".to_string()],
+                1,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            );
+        }
+        assert_eq!(contract.finalize_expired(10), 0);
+
+        context.block_timestamp = now + 2500;
+        testing_env!(context);
+        assert_eq!(contract.finalize_expired(1), 1);
+        assert_eq!(contract.get_proposal(1).unwrap()[5], ProposalState::Rejected.to_string());
+        assert_eq!(contract.get_proposal(2).unwrap()[5], ProposalState::Open.to_string());
+        assert_eq!(contract.finalize_expired(10), 1);
+        assert_eq!(contract.get_proposal(2).unwrap()[5], ProposalState::Rejected.to_string());
+        assert_eq!(contract.get_proposal(0).unwrap()[5], ProposalState::Open.to_string());
+        assert_eq!(contract.get_open_proposal_count(), 1);
+    }
+
+    #[test]
+    fn test_update_status_twice() {
+        let mut context = get_context("alice".parse().unwrap());
+        testing_env!(context.clone());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        let now = env::block_timestamp();
+        contract.create_proposal(
+            "Test Proposal".to_string(),
+            "This is a test proposal".to_string(),
+            now + 1000,
+            vec!["Yes".to_string(), "No".to_string()],
+            1,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        contract.process_vote_callback(0, "alice".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
+
+        context.block_timestamp = now + 1001;
+        testing_env!(context);
+        assert_eq!(contract.update_status(0), ProposalState::Passed);
+        assert_eq!(contract.update_status(0), ProposalState::Passed);
+        assert_eq!(contract.get_proposal(0).unwrap()[5], ProposalState::Passed.to_string());
+        assert_eq!(contract.get_open_proposal_count(), 0);
     }
 
     #[test]
@@ -221,23 +684,113 @@ mod tests {
     fn test_update_status_invalid_proposal() {
         let context = get_context("alice".parse().unwrap());
         testing_env!(context);
-        let mut contract = ProposalContract::new("token-contract".parse().unwrap());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
         contract.update_status(0);
     }
 
+    #[test]
+    fn test_proposal_with_linked_content() {
+        let context = get_context("alice".parse().unwrap());
+        testing_env!(context);
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        let full_text = "The full proposal text, too long to keep on-chain".to_string();
+        let content_hash: Base64VecU8 = env::sha256(full_text.as_bytes()).into();
+        let proposal_id = contract.create_proposal(
+            "Test Proposal".to_string(),
+            "Short summary".to_string(),
+            env::block_timestamp() + 1000,
+            vec!["Yes".to_string(), "No".to_string()],
+            1,
+            Some("ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi".to_string()),
+            Some(content_hash.clone()),
+            None,
+            None,
+            None,
+            None,
+        );
+
+        let content = contract.get_proposal_content(proposal_id).expect("Proposal not found");
+        assert_eq!(content.summary, "Short summary");
+        assert_eq!(content.content_hash, Some(content_hash));
+        assert!(contract.verify_content(proposal_id, full_text));
+        assert!(!contract.verify_content(proposal_id, "Tampered text".to_string()));
+    }
+
+    #[test]
+    fn test_set_gas_config() {
+        let context = get_context("proposal_contract".parse().unwrap());
+        testing_env!(context);
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        assert_eq!(contract.get_gas_config(), GasConfig::default());
+        let gas_config = GasConfig { vote_callback: Gas::from_tgas(40), ..Default::default() };
+        contract.set_gas_config(gas_config.clone());
+        assert_eq!(contract.get_gas_config(), gas_config);
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many options, the maximum is 2")]
+    fn test_create_proposal_uses_configured_limits() {
+        let context = get_context("alice".parse().unwrap());
+        testing_env!(context);
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), Some("alice".parse().unwrap()));
+        contract.set_limits(ProposalLimits { max_options: 2, ..Default::default() });
+        assert_eq!(contract.get_limits().max_options, 2);
+        contract.create_proposal(
+            "Test Proposal".to_string(),
+            "This is a test proposal".to_string(),
+            env::block_timestamp() + 1000,
+            vec!["Yes".to_string(), "No".to_string(), "Maybe".to_string()],
+            1,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient deposit for storage")]
+    fn test_create_proposal_insufficient_deposit() {
+        let mut context = get_context("alice".parse().unwrap());
+        context.attached_deposit = NearToken::from_yoctonear(1);
+        testing_env!(context);
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        contract.create_proposal(
+            "Test Proposal".to_string(),
+            "This is a test proposal".to_string(),
+            env::block_timestamp() + 1000,
+            vec!["Yes".to_string(), "No".to_string()],
+            1,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+    }
+
     #[test]
     #[should_panic(expected = "Deadline must be in the future")]
     fn test_create_proposal_invalid_deadline() {
         let context = get_context("alice".parse().unwrap());
         testing_env!(context);
         let options_vec = vec!["Yes".to_string(), "No".to_string()];
-        let mut contract = ProposalContract::new("token-contract".parse().unwrap());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
         contract.create_proposal(
             "Test Proposal".to_string(),
             "This is a test proposal".to_string(),
             env::block_timestamp(),
             options_vec,
             1,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
     }
 }
\ No newline at end of file
@@ -0,0 +1,203 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use serde::{Serialize, Deserialize};
+
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+// Bounds enforced on new proposals, lengths are in bytes
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct ProposalLimits {
+    pub max_title_length: u32,
+    pub max_description_length: u32,
+    // Applies to the description instead when the full text is linked off-chain
+    pub max_summary_length: u32,
+    pub max_link_length: u32,
+    pub max_option_length: u32,
+    pub max_options: u32,
+    pub max_comment_length: u32,
+    pub max_tags: u32,
+    pub max_tag_length: u32,
+    // Furthest a deadline can be from the creation time, in nanoseconds
+    pub max_deadline_window: u64,
+}
+
+impl Default for ProposalLimits {
+    fn default() -> Self {
+        Self {
+            max_title_length: 128,
+            max_description_length: 4096,
+            max_summary_length: 512,
+            max_link_length: 256,
+            max_option_length: 64,
+            max_options: 10,
+            max_comment_length: 1024,
+            max_tags: 5,
+            max_tag_length: 32,
+            max_deadline_window: 30 * NANOS_PER_DAY,
+        }
+    }
+}
+
+impl ProposalLimits {
+    // Panics with a description of the first rule the proposal breaks
+    pub fn assert_valid(&self, title: &str, description: &str, deadline: u64, options: &[String], now: u64) {
+        assert!(title.len() <= self.max_title_length as usize, "Title exceeds {} bytes", self.max_title_length);
+        assert!(
+            description.len() <= self.max_description_length as usize,
+            "Description exceeds {} bytes",
+            self.max_description_length
+        );
+        assert!(options.len() <= self.max_options as usize, "Too many options, the maximum is {}", self.max_options);
+        for (index, option) in options.iter().enumerate() {
+            assert!(!option.trim().is_empty(), "Options cannot be empty");
+            assert!(option.len() <= self.max_option_length as usize, "Option exceeds {} bytes", self.max_option_length);
+            assert!(
+                !options[..index].iter().any(|other| other.trim() == option.trim()),
+                "Duplicate option: {}",
+                option
+            );
+        }
+        assert!(
+            deadline <= now.saturating_add(self.max_deadline_window),
+            "Deadline is too far in the future, the maximum window is {} ns",
+            self.max_deadline_window
+        );
+    }
+
+    pub fn assert_valid_content(&self, description: &str, link: Option<&str>, content_hash: Option<&[u8]>) {
+        if let Some(link) = link {
+            assert!(!link.trim().is_empty(), "Link cannot be empty");
+            assert!(link.len() <= self.max_link_length as usize, "Link exceeds {} bytes", self.max_link_length);
+            assert!(content_hash.is_some(), "A content hash is required with a link");
+        }
+        if let Some(content_hash) = content_hash {
+            assert!(content_hash.len() == 32, "Content hash must be a 32 byte sha256 digest");
+            assert!(
+                description.len() <= self.max_summary_length as usize,
+                "Summary exceeds {} bytes",
+                self.max_summary_length
+            );
+        }
+    }
+
+    pub fn assert_valid_tags(&self, tags: &[String]) {
+        assert!(tags.len() <= self.max_tags as usize, "Too many tags, the maximum is {}", self.max_tags);
+        for (index, tag) in tags.iter().enumerate() {
+            assert!(!tag.trim().is_empty(), "Tags cannot be empty");
+            assert!(tag.len() <= self.max_tag_length as usize, "Tag exceeds {} bytes", self.max_tag_length);
+            assert!(!tags[..index].contains(tag), "Duplicate tag: {}", tag);
+        }
+    }
+
+    pub fn assert_valid_comment(&self, body: &str) {
+        assert!(!body.trim().is_empty(), "Comment cannot be empty");
+        assert!(body.len() <= self.max_comment_length as usize, "Comment exceeds {} bytes", self.max_comment_length);
+    }
+}
+
+// The rest of this file holds the inline tests for the code above
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_600_000_000_000;
+
+    fn options(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_valid_proposal() {
+        ProposalLimits::default().assert_valid("title", "description", NOW + 1000, &options(&["Yes", "No"]), NOW);
+    }
+
+    #[test]
+    #[should_panic(expected = "Title exceeds 128 bytes")]
+    fn test_title_too_long() {
+        let title = "t".repeat(129);
+        ProposalLimits::default().assert_valid(&title, "description", NOW + 1000, &options(&["Yes", "No"]), NOW);
+    }
+
+    #[test]
+    #[should_panic(expected = "Description exceeds 4096 bytes")]
+    fn test_description_too_long() {
+        let description = "d".repeat(4097);
+        ProposalLimits::default().assert_valid("title", &description, NOW + 1000, &options(&["Yes", "No"]), NOW);
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many options, the maximum is 3")]
+    fn test_too_many_options() {
+        let limits = ProposalLimits { max_options: 3, ..Default::default() };
+        limits.assert_valid("title", "description", NOW + 1000, &options(&["A", "B", "C", "D"]), NOW);
+    }
+
+    #[test]
+    #[should_panic(expected = "Option exceeds 64 bytes")]
+    fn test_option_too_long() {
+        let long_option = "o".repeat(65);
+        ProposalLimits::default().assert_valid("title", "description", NOW + 1000, &options(&["Yes", &long_option]), NOW);
+    }
+
+    #[test]
+    #[should_panic(expected = "Options cannot be empty")]
+    fn test_empty_option() {
+        ProposalLimits::default().assert_valid("title", "description", NOW + 1000, &options(&["Yes", "  "]), NOW);
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate option: Yes")]
+    fn test_duplicate_option() {
+        ProposalLimits::default().assert_valid("title", "description", NOW + 1000, &options(&["Yes", "No", "Yes"]), NOW);
+    }
+
+    #[test]
+    fn test_valid_content() {
+        let hash = [0u8; 32];
+        ProposalLimits::default().assert_valid_content("summary", Some("ipfs://bafy"), Some(&hash));
+        ProposalLimits::default().assert_valid_content(&"d".repeat(4096), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "A content hash is required with a link")]
+    fn test_link_without_hash() {
+        ProposalLimits::default().assert_valid_content("summary", Some("ipfs://bafy"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Content hash must be a 32 byte sha256 digest")]
+    fn test_invalid_content_hash() {
+        ProposalLimits::default().assert_valid_content("summary", Some("ipfs://bafy"), Some(&[0u8; 20]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Summary exceeds 512 bytes")]
+    fn test_summary_too_long() {
+        ProposalLimits::default().assert_valid_content(&"d".repeat(513), Some("ipfs://bafy"), Some(&[0u8; 32]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many tags, the maximum is 5")]
+    fn test_too_many_tags() {
+        ProposalLimits::default().assert_valid_tags(&options(&["a", "b", "c", "d", "e", "f"]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate tag: q3")]
+    fn test_duplicate_tag() {
+        ProposalLimits::default().assert_valid_tags(&options(&["q3", "grants", "q3"]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Comment exceeds 1024 bytes")]
+    fn test_comment_too_long() {
+        ProposalLimits::default().assert_valid_comment(&"c".repeat(1025));
+    }
+
+    #[test]
+    #[should_panic(expected = "Deadline is too far in the future")]
+    fn test_deadline_beyond_window() {
+        let limits = ProposalLimits { max_deadline_window: 1000, ..Default::default() };
+        limits.assert_valid("title", "description", NOW + 1001, &options(&["Yes", "No"]), NOW);
+    }
+}
@@ -0,0 +1,335 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, AccountId, Promise, PromiseError};
+use serde::{Deserialize, Serialize};
+use crate::conviction::ONE_YOCTO;
+use crate::vote::ft_contract;
+use crate::{ProposalContract, ProposalContractExt, ProposalState, VotingMode};
+
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+// 1000 FDAO at 24 decimals
+const DEFAULT_OBJECTION_THRESHOLD: u128 = 1_000 * 10u128.pow(24);
+
+// When objections turn an optimistic proposal into a regular vote
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct OptimisticConfig {
+    // FDAO locked by the objectors that has to be exceeded to escalate
+    pub objection_threshold: U128,
+    // Length of the vote after an escalation, in nanoseconds
+    pub escalation_period: u64,
+}
+
+impl Default for OptimisticConfig {
+    fn default() -> Self {
+        Self {
+            objection_threshold: U128(DEFAULT_OBJECTION_THRESHOLD),
+            escalation_period: 3 * NANOS_PER_DAY,
+        }
+    }
+}
+
+#[near_bindgen]
+impl ProposalContract {
+    // Take back the FDAO locked by an objection once the proposal is finalized, along with the storage
+    // of the objection. The attached yoctoNEAR is forwarded to `ft_transfer`.
+    #[payable]
+    pub fn withdraw_objection(&mut self, proposal_id: u64) -> Promise {
+        assert!(env::attached_deposit() == ONE_YOCTO, "Requires attached deposit of exactly 1 yoctoNEAR");
+        let objector = env::predecessor_account_id();
+        let proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        assert!(proposal.state != ProposalState::Open, "Objections are locked until the proposal is finalized");
+        let initial_storage_usage = env::storage_usage();
+        let amount = self.objections.remove(&(proposal_id, objector.clone())).expect("No objection found");
+        self.flush_collections();
+        Self::release_storage(initial_storage_usage, &objector);
+
+        ft_contract::ext(self.token_contract_id.clone())
+            .with_attached_deposit(ONE_YOCTO)
+            .with_static_gas(self.gas_config.ft_transfer)
+            .with_unused_gas_weight(0)
+            .ft_transfer(objector.clone(), amount, None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(self.gas_config.withdraw_callback)
+                    .with_unused_gas_weight(1)
+                    .process_objection_withdraw_callback(proposal_id, objector, amount),
+            )
+    }
+
+    // Lock the objection again if the transfer failed, it can be withdrawn later
+    #[private]
+    pub fn process_objection_withdraw_callback(
+        &mut self,
+        proposal_id: u64,
+        objector: AccountId,
+        amount: U128,
+        #[callback_result] result: Result<(), PromiseError>,
+    ) {
+        if let Err(e) = result {
+            env::log_str(&format!("Failed to return the objection of {}: {:?}", objector, e));
+            let initial_storage_usage = env::storage_usage();
+            self.objections.insert((proposal_id, objector.clone()), amount);
+            self.flush_collections();
+            self.recharge_stake_storage(initial_storage_usage, &objector);
+        }
+    }
+
+    // FDAO locked by the objectors
+    pub fn get_objection_weight(&self, proposal_id: u64) -> U128 {
+        self.proposals.get(&proposal_id).expect("Proposal not found").objection_weight
+    }
+
+    pub fn get_objection(&self, proposal_id: u64, account_id: AccountId) -> Option<U128> {
+        self.objections.get(&(proposal_id, account_id)).copied()
+    }
+
+    // When the proposal turned into a regular vote, None while it is still passing optimistically
+    pub fn get_escalated_at(&self, proposal_id: u64) -> Option<u64> {
+        self.proposals.get(&proposal_id).expect("Proposal not found").escalated_at
+    }
+
+    pub fn set_optimistic_config(&mut self, optimistic_config: OptimisticConfig) {
+        self.assert_governance();
+        assert!(optimistic_config.objection_threshold.0 > 0, "Objection threshold must be positive");
+        assert!(optimistic_config.escalation_period > 0, "Escalation period must be positive");
+        env::log_str(&format!("Optimistic config updated to {:?}", optimistic_config));
+        self.optimistic_config = optimistic_config;
+    }
+
+    pub fn get_optimistic_config(&self) -> OptimisticConfig {
+        self.optimistic_config.clone()
+    }
+}
+
+impl ProposalContract {
+    // Called by `ft_on_transfer`, the objected FDAO stays locked until the proposal is finalized
+    pub(crate) fn internal_object(&mut self, proposal_id: u64, objector: &AccountId, amount: u128) {
+        self.assert_not_paused("object");
+        self.assert_can_object(proposal_id, objector);
+        let proposal = self.proposals.get_mut(&proposal_id).expect("Proposal not found");
+        let objection_index = proposal.objection_count;
+        proposal.objection_count += 1;
+        proposal.objection_weight = U128(proposal.objection_weight.0.saturating_add(amount));
+        let objection_weight = proposal.objection_weight.0;
+        self.objections.insert((proposal_id, objector.clone()), U128(amount));
+        self.objectors.insert((proposal_id, objection_index), objector.clone());
+        env::log_str(&format!("{} objected to proposal {} with {}", objector, proposal_id, amount));
+
+        if objection_weight > self.optimistic_config.objection_threshold.0 {
+            self.escalate(proposal_id);
+        }
+    }
+
+    fn assert_can_object(&self, proposal_id: u64, objector: &AccountId) {
+        assert!(!self.objections.contains_key(&(proposal_id, objector.clone())), "Account has already objected");
+        let proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        assert!(proposal.voting_mode == VotingMode::Optimistic, "Proposal is not optimistic");
+        assert!(proposal.state == ProposalState::Open, "Proposal is not open");
+        assert!(proposal.escalated_at.is_none(), "Proposal has already been escalated to a vote");
+        assert!(env::block_timestamp() <= proposal.deadline, "Objection period has ended");
+    }
+
+    // Open a regular vote that ends one escalation period from now
+    fn escalate(&mut self, proposal_id: u64) {
+        let now = env::block_timestamp();
+        let deadline = now.saturating_add(self.optimistic_config.escalation_period);
+        let proposal = self.proposals.get_mut(&proposal_id).expect("Proposal not found");
+        let previous_deadline = proposal.deadline;
+        proposal.escalated_at = Some(now);
+        proposal.deadline = deadline;
+        self.open_by_deadline.remove(&(previous_deadline, proposal_id));
+        self.open_by_deadline.insert((deadline, proposal_id), ());
+        env::log_str(&format!("Proposal {} escalated to a vote ending at {}", proposal_id, deadline));
+    }
+}
+
+// The rest of this file holds the inline tests for the code above
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::ARCHIVE_RETENTION_PERIOD;
+    use crate::Ballot;
+    use near_sdk::mock::MockAction;
+    use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+    use near_sdk::{serde_json, testing_env, NearToken};
+
+    const DEADLINE: u64 = 1_000;
+    const ESCALATION_PERIOD: u64 = 5_000;
+
+    fn set_context(block_timestamp: u64) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("proposal".parse().unwrap())
+            .signer_account_id("admin".parse().unwrap())
+            .predecessor_account_id("proposal".parse().unwrap())
+            .attached_deposit(NearToken::from_near(1))
+            .block_timestamp(block_timestamp)
+            .build());
+    }
+
+    fn setup_optimistic_proposal() -> ProposalContract {
+        set_context(0);
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        contract.set_optimistic_config(OptimisticConfig {
+            objection_threshold: U128(100),
+            escalation_period: ESCALATION_PERIOD,
+        });
+        contract.create_proposal(
+            "title".to_string(),
+            "description".to_string(),
+            DEADLINE,
+            vec!["Approve".to_string(), "Reject".to_string()],
+            1,
+            None,
+            None,
+            None,
+            None,
+            Some(VotingMode::Optimistic),
+            None,
+        );
+        contract
+    }
+
+    // Lock `amount` FDAO behind an objection to proposal 0, as the token's `ft_transfer_call` would
+    fn object(contract: &mut ProposalContract, account: &str, amount: u128) {
+        let block_timestamp = env::block_timestamp();
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("proposal".parse().unwrap())
+            .predecessor_account_id(account.parse().unwrap())
+            .attached_deposit(NearToken::from_millinear(10))
+            .block_timestamp(block_timestamp)
+            .build());
+        contract.deposit_stake_storage();
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("proposal".parse().unwrap())
+            .predecessor_account_id("token-contract".parse().unwrap())
+            .block_timestamp(block_timestamp)
+            .build());
+        let message = serde_json::json!({ "object_to": 0 }).to_string();
+        contract.ft_on_transfer(account.parse().unwrap(), U128(amount), message);
+        set_context(block_timestamp);
+    }
+
+    fn withdraw_objection(contract: &mut ProposalContract, account: &str, block_timestamp: u64) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("proposal".parse().unwrap())
+            .predecessor_account_id(account.parse().unwrap())
+            .attached_deposit(ONE_YOCTO)
+            .block_timestamp(block_timestamp)
+            .build());
+        contract.withdraw_objection(0);
+    }
+
+    // Receivers and amounts of the `ft_transfer` calls made so far
+    fn ft_transfers() -> Vec<(String, String)> {
+        get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                MockAction::FunctionCallWeight { method_name, args, .. } if method_name == b"ft_transfer" => {
+                    let args: serde_json::Value = serde_json::from_slice(&args).unwrap();
+                    Some((args["receiver_id"].as_str().unwrap().to_string(), args["amount"].as_str().unwrap().to_string()))
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_passes_without_enough_objections() {
+        let mut contract = setup_optimistic_proposal();
+        object(&mut contract, "alice", 60);
+        object(&mut contract, "bob", 40);
+        assert_eq!(contract.get_objection_weight(0), U128(100));
+        assert_eq!(contract.get_escalated_at(0), None);
+
+        set_context(DEADLINE + 1);
+        assert_eq!(contract.update_status(0), ProposalState::Passed);
+    }
+
+    #[test]
+    fn test_objections_escalate_to_a_vote() {
+        let mut contract = setup_optimistic_proposal();
+        set_context(200);
+        object(&mut contract, "alice", 101);
+        assert_eq!(contract.get_escalated_at(0), Some(200));
+
+        // The original deadline no longer finalizes it
+        set_context(DEADLINE + 1);
+        assert_eq!(contract.finalize_expired(10), 0);
+        contract.process_vote_callback(0, "bob".parse().unwrap(), Ballot::NoWithVeto, Ok(U128(1)));
+
+        set_context(200 + ESCALATION_PERIOD + 1);
+        assert_eq!(contract.update_status(0), ProposalState::Rejected);
+    }
+
+    #[test]
+    fn test_escalated_vote_passes_only_on_approval() {
+        let mut contract = setup_optimistic_proposal();
+        object(&mut contract, "alice", 101);
+        contract.process_vote_callback(0, "bob".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
+        contract.process_vote_callback(0, "carol".parse().unwrap(), Ballot::Option(1), Ok(U128(1)));
+        contract.process_vote_callback(0, "dave".parse().unwrap(), Ballot::Option(1), Ok(U128(1)));
+
+        // The objectors win the escalated vote with "Reject"
+        set_context(ESCALATION_PERIOD + 1);
+        assert_eq!(contract.update_status(0), ProposalState::Rejected);
+    }
+
+    #[test]
+    #[should_panic(expected = "Objection threshold must be positive")]
+    fn test_zero_objection_threshold() {
+        let mut contract = setup_optimistic_proposal();
+        contract.set_optimistic_config(OptimisticConfig { objection_threshold: U128(0), ..Default::default() });
+    }
+
+    #[test]
+    #[should_panic(expected = "Optimistic proposal has not been escalated to a vote")]
+    fn test_vote_before_escalation() {
+        let mut contract = setup_optimistic_proposal();
+        contract.process_vote_callback(0, "bob".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Proposal has already been escalated to a vote")]
+    fn test_object_after_escalation() {
+        let mut contract = setup_optimistic_proposal();
+        object(&mut contract, "alice", 500);
+        object(&mut contract, "bob", 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Objections are locked until the proposal is finalized")]
+    fn test_withdraw_objection_while_open() {
+        let mut contract = setup_optimistic_proposal();
+        object(&mut contract, "alice", 60);
+        withdraw_objection(&mut contract, "alice", 0);
+    }
+
+    #[test]
+    fn test_withdraw_objection_after_finalization() {
+        let mut contract = setup_optimistic_proposal();
+        object(&mut contract, "alice", 60);
+        set_context(DEADLINE + 1);
+        contract.update_status(0);
+        withdraw_objection(&mut contract, "alice", DEADLINE + 1);
+        assert_eq!(contract.get_objection(0, "alice".parse().unwrap()), None);
+        assert_eq!(ft_transfers(), vec![("alice".to_string(), "60".to_string())]);
+        // The weight stays on record
+        assert_eq!(contract.get_objection_weight(0), U128(60));
+    }
+
+    #[test]
+    fn test_archive_returns_locked_objections() {
+        let mut contract = setup_optimistic_proposal();
+        object(&mut contract, "alice", 60);
+        object(&mut contract, "bob", 30);
+        set_context(DEADLINE + 1);
+        contract.update_status(0);
+        withdraw_objection(&mut contract, "alice", DEADLINE + 1);
+
+        set_context(DEADLINE + ARCHIVE_RETENTION_PERIOD + 1);
+        assert!(contract.archive_proposal(0, None).is_some());
+        assert_eq!(ft_transfers(), vec![("bob".to_string(), "30".to_string())]);
+    }
+}
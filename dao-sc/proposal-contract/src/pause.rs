@@ -0,0 +1,157 @@
+use near_sdk::{env, near_bindgen, AccountId};
+use crate::ProposalContract;
+use crate::ProposalContractExt;
+
+// Methods the guardian can pause, commit-reveal and ballot kinds pause together with `vote`
+pub const PAUSABLE_METHODS: [&str; 10] = [
+    "create_proposal",
+    "vote",
+    "object",
+    "commit_vote",
+    "reveal_vote",
+    "add_comment",
+    "update_status",
+    "finalize_expired",
+    "archive_proposal",
+    "update_conviction",
+];
+
+#[near_bindgen]
+impl ProposalContract {
+    // The guardian can only pause, unpausing goes through governance
+    pub fn set_guardian(&mut self, guardian: Option<AccountId>) {
+        self.assert_governance();
+        env::log_str(&format!("Guardian set to {:?}", guardian));
+        self.guardian = guardian;
+    }
+
+    pub fn get_guardian(&self) -> Option<AccountId> {
+        self.guardian.clone()
+    }
+
+    pub fn pause(&mut self, methods: Vec<String>) {
+        let caller = env::predecessor_account_id();
+        assert!(
+            self.guardian.as_ref() == Some(&caller) || self.is_governance(&caller),
+            "Only the guardian can pause"
+        );
+        for method in methods {
+            assert!(PAUSABLE_METHODS.contains(&method.as_str()), "Unknown pausable method: {}", method);
+            if !self.paused_methods.contains(&method) {
+                env::log_str(&format!("Method {} paused by {}", method, caller));
+                self.paused_methods.push(method);
+            }
+        }
+    }
+
+    pub fn unpause(&mut self, methods: Vec<String>) {
+        self.assert_governance();
+        for method in methods {
+            env::log_str(&format!("Method {} unpaused", method));
+            self.paused_methods.retain(|paused| paused != &method);
+        }
+    }
+
+    pub fn get_paused_methods(&self) -> Vec<String> {
+        self.paused_methods.clone()
+    }
+}
+
+impl ProposalContract {
+    pub(crate) fn assert_not_paused(&self, method: &str) {
+        assert!(!self.paused_methods.iter().any(|paused| paused == method), "Method {} is paused", method);
+    }
+}
+
+// The rest of this file holds the inline tests for the code above
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{testing_env, NearToken};
+
+    fn set_caller(account: &str) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("proposal".parse().unwrap())
+            .signer_account_id(account.parse().unwrap())
+            .predecessor_account_id(account.parse().unwrap())
+            .account_balance(NearToken::from_near(10))
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+    }
+
+    fn setup_contract() -> ProposalContract {
+        set_caller("proposal");
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        contract.set_guardian(Some("guardian".parse().unwrap()));
+        contract
+    }
+
+    fn create(contract: &mut ProposalContract) -> u64 {
+        contract.create_proposal(
+            "title".to_string(),
+            "description".to_string(),
+            1000,
+            vec!["Yes".to_string(), "No".to_string()],
+            1,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    #[should_panic(expected = "Method create_proposal is paused")]
+    fn test_paused_create_proposal() {
+        let mut contract = setup_contract();
+        set_caller("guardian");
+        contract.pause(vec!["create_proposal".to_string()]);
+        create(&mut contract);
+    }
+
+    #[test]
+    #[should_panic(expected = "Method vote is paused")]
+    fn test_paused_vote() {
+        let mut contract = setup_contract();
+        create(&mut contract);
+        set_caller("guardian");
+        contract.pause(vec!["vote".to_string()]);
+        set_caller("alice");
+        contract.vote(0, "alice".parse().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_pause_is_per_method() {
+        let mut contract = setup_contract();
+        set_caller("guardian");
+        contract.pause(vec!["vote".to_string()]);
+        set_caller("alice");
+        assert_eq!(create(&mut contract), 0);
+
+        set_caller("proposal");
+        contract.unpause(vec!["vote".to_string()]);
+        assert!(contract.get_paused_methods().is_empty());
+        set_caller("alice");
+        contract.vote(0, "alice".parse().unwrap(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the guardian can pause")]
+    fn test_pause_not_guardian() {
+        let mut contract = setup_contract();
+        set_caller("alice");
+        contract.pause(vec!["vote".to_string()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown pausable method: get_votes")]
+    fn test_pause_unknown_method() {
+        let mut contract = setup_contract();
+        set_caller("guardian");
+        contract.pause(vec!["get_votes".to_string()]);
+    }
+}
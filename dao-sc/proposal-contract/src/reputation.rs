@@ -0,0 +1,198 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{env, near_bindgen, AccountId};
+use serde::{Deserialize, Serialize};
+use crate::ProposalContract;
+use crate::ProposalContractExt;
+
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+// How reputation is earned and how fast it fades
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct ReputationConfig {
+    // Earned for each ballot cast
+    pub vote_reward: u64,
+    // Earned by the proposer when a proposal passes
+    pub passed_proposal_reward: u64,
+    // Length of a decay epoch, in nanoseconds
+    pub epoch_length: u64,
+    // Share of the reputation lost at the end of each epoch
+    pub decay_percent: u8,
+}
+
+impl Default for ReputationConfig {
+    fn default() -> Self {
+        Self {
+            vote_reward: 10,
+            passed_proposal_reward: 50,
+            epoch_length: 7 * NANOS_PER_DAY,
+            decay_percent: 10,
+        }
+    }
+}
+
+// Reputation as of the start of `epoch`, decay for later epochs is applied when it is read
+#[derive(BorshDeserialize, BorshSerialize, PartialEq, Debug, Clone)]
+pub struct ReputationEntry {
+    score: u64,
+    epoch: u64,
+}
+
+impl ReputationConfig {
+    fn current_epoch(&self) -> u64 {
+        env::block_timestamp() / self.epoch_length
+    }
+
+    fn decayed(&self, entry: &ReputationEntry, epoch: u64) -> u64 {
+        let mut score = entry.score;
+        for _ in entry.epoch..epoch {
+            if score == 0 || self.decay_percent == 0 {
+                break;
+            }
+            score -= (score as u128 * self.decay_percent as u128).div_ceil(100) as u64;
+        }
+        score
+    }
+}
+
+#[near_bindgen]
+impl ProposalContract {
+    // Reputation of an account with decay applied up to the current epoch
+    pub fn get_reputation(&self, account_id: AccountId) -> u64 {
+        self.reputation
+            .get(&account_id)
+            .map_or(0, |entry| self.reputation_config.decayed(entry, self.reputation_config.current_epoch()))
+    }
+
+    pub fn set_reputation_config(&mut self, reputation_config: ReputationConfig) {
+        self.assert_governance();
+        assert!(reputation_config.epoch_length > 0, "Epoch length must be positive");
+        assert!(reputation_config.decay_percent <= 100, "Decay cannot exceed 100 percent");
+        env::log_str(&format!("Reputation config updated to {:?}", reputation_config));
+        self.reputation_config = reputation_config;
+    }
+
+    pub fn get_reputation_config(&self) -> ReputationConfig {
+        self.reputation_config.clone()
+    }
+}
+
+impl ProposalContract {
+    pub(crate) fn award_reputation(&mut self, account_id: &AccountId, amount: u64) {
+        if amount == 0 {
+            return;
+        }
+        let epoch = self.reputation_config.current_epoch();
+        let score = self.get_reputation(account_id.clone()).saturating_add(amount);
+        self.reputation.insert(account_id.clone(), ReputationEntry { score, epoch });
+    }
+}
+
+// The rest of this file holds the inline tests for the code above
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ballot, ProposalState, VotingWeight};
+    use near_sdk::json_types::U128;
+    use near_sdk::mock::MockAction;
+    use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+    use near_sdk::{testing_env, NearToken};
+
+    const EPOCH: u64 = 1_000;
+
+    fn set_context(block_timestamp: u64) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("proposal".parse().unwrap())
+            .signer_account_id("admin".parse().unwrap())
+            .predecessor_account_id("proposal".parse().unwrap())
+            .attached_deposit(NearToken::from_near(1))
+            .block_timestamp(block_timestamp)
+            .build());
+    }
+
+    fn setup_contract() -> ProposalContract {
+        set_context(0);
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        contract.set_reputation_config(ReputationConfig { epoch_length: EPOCH, ..Default::default() });
+        contract
+    }
+
+    fn create_proposal(contract: &mut ProposalContract, deadline: u64, voting_weight: VotingWeight) -> u64 {
+        contract.create_proposal(
+            "title".to_string(),
+            "description".to_string(),
+            deadline,
+            vec!["option1".to_string(), "option2".to_string()],
+            1,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(voting_weight),
+        )
+    }
+
+    #[test]
+    fn test_reputation_decays_per_epoch() {
+        let mut contract = setup_contract();
+        contract.award_reputation(&"alice".parse().unwrap(), 100);
+        set_context(EPOCH - 1);
+        assert_eq!(contract.get_reputation("alice".parse().unwrap()), 100);
+        set_context(2 * EPOCH);
+        assert_eq!(contract.get_reputation("alice".parse().unwrap()), 81);
+    }
+
+    #[test]
+    fn test_voting_and_passing_earn_reputation() {
+        let mut contract = setup_contract();
+        create_proposal(&mut contract, 500, VotingWeight::OnePerVoter);
+        contract.process_vote_callback(0, "alice".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
+        assert_eq!(contract.get_reputation("alice".parse().unwrap()), 10);
+
+        set_context(501);
+        contract.update_status(0);
+        contract.update_status(0);
+        assert_eq!(contract.get_reputation("admin".parse().unwrap()), 50);
+    }
+
+    #[test]
+    fn test_proposals_outside_governance_earn_no_reputation() {
+        let mut contract = setup_contract();
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("proposal".parse().unwrap())
+            .signer_account_id("mallory".parse().unwrap())
+            .predecessor_account_id("mallory".parse().unwrap())
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        create_proposal(&mut contract, 500, VotingWeight::OnePerVoter);
+        contract.process_vote_callback(0, "mallory".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
+        assert_eq!(contract.get_reputation("mallory".parse().unwrap()), 0);
+
+        set_context(501);
+        assert_eq!(contract.update_status(0), ProposalState::Passed);
+        assert_eq!(contract.get_reputation("mallory".parse().unwrap()), 0);
+    }
+
+    #[test]
+    fn test_reputation_weighted_ballot() {
+        let mut contract = setup_contract();
+        contract.award_reputation(&"alice".parse().unwrap(), 40);
+        create_proposal(&mut contract, 500, VotingWeight::Reputation { balance: None });
+        contract.process_vote_callback(0, "alice".parse().unwrap(), Ballot::Option(1), Ok(U128(1)));
+        assert_eq!(contract.get_votes(0)[1], ("option2".to_string(), 40));
+        assert_eq!(contract.get_reputation("alice".parse().unwrap()), 50);
+    }
+
+    #[test]
+    fn test_reputation_ballot_without_reputation() {
+        let mut contract = setup_contract();
+        create_proposal(&mut contract, 500, VotingWeight::Reputation { balance: None });
+        contract.process_vote_callback(0, "alice".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
+        assert_eq!(contract.get_vote(0, "alice".parse().unwrap()), None);
+        assert!(get_created_receipts().iter().flat_map(|receipt| receipt.actions.iter()).any(|action| matches!(
+            action,
+            MockAction::Transfer { deposit, .. } if *deposit == NearToken::from_near(1)
+        )));
+    }
+}
@@ -0,0 +1,119 @@
+use near_sdk::{env, AccountId, NearToken, Promise};
+use crate::ProposalContract;
+
+// Smallest deposit accepted by `vote`, enough for the ballot entries written by the callback
+pub const MIN_VOTE_DEPOSIT: NearToken = NearToken::from_millinear(10);
+
+// Smallest deposit accepted by `add_comment`, longer comments need more
+pub const MIN_COMMENT_DEPOSIT: NearToken = NearToken::from_millinear(10);
+
+impl ProposalContract {
+    // Keep the attached deposit needed for the storage used since `initial_storage_usage`
+    // and refund the excess to `payer`. Returns the amount kept.
+    pub(crate) fn charge_storage(initial_storage_usage: u64, payer: &AccountId) -> NearToken {
+        Self::charge_storage_and_bond(initial_storage_usage, payer, NearToken::from_near(0))
+    }
+
+    // Same as `charge_storage`, also keeping `bond` out of the refund
+    pub(crate) fn charge_storage_and_bond(initial_storage_usage: u64, payer: &AccountId, bond: NearToken) -> NearToken {
+        let used_bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+        let required = env::storage_byte_cost().saturating_mul(used_bytes as u128).saturating_add(bond);
+        let attached = env::attached_deposit();
+        assert!(
+            attached >= required,
+            "Insufficient deposit for storage: attached {}, required {}",
+            attached,
+            required
+        );
+
+        let refund = attached.saturating_sub(required);
+        if !refund.is_zero() {
+            Promise::new(payer.clone()).transfer(refund);
+        }
+        required
+    }
+
+    // Write pending collection changes so `env::storage_usage` reflects them
+    pub(crate) fn flush_collections(&mut self) {
+        self.proposals.flush();
+        self.votes.flush();
+        self.voters.flush();
+        self.archived.flush();
+        self.open_by_deadline.flush();
+        self.comments.flush();
+        self.moderators.flush();
+        self.categories.flush();
+        self.proposals_by_category.flush();
+        self.proposals_by_tag.flush();
+        self.votes_by_account.flush();
+        self.voter_stats.flush();
+        self.commitments.flush();
+        self.committers.flush();
+        self.reputation.flush();
+        self.conviction_proposals.flush();
+        self.conviction_stakes.flush();
+        self.stake_storage.flush();
+        self.objections.flush();
+        self.objectors.flush();
+    }
+
+    // Send the deposit forwarded to a callback back to the signer when the callback turns the action down,
+    // a panic would leave it with the contract
+    pub(crate) fn refund_deposit(reason: &str) {
+        env::log_str(reason);
+        let deposit = env::attached_deposit();
+        if !deposit.is_zero() {
+            Promise::new(env::signer_account_id()).transfer(deposit);
+        }
+    }
+
+    // Return the deposit held for storage freed since `initial_storage_usage` to `payer`
+    pub(crate) fn release_storage(initial_storage_usage: u64, payer: &AccountId) -> NearToken {
+        let freed_bytes = initial_storage_usage.saturating_sub(env::storage_usage());
+        let refund = env::storage_byte_cost().saturating_mul(freed_bytes as u128);
+        if !refund.is_zero() {
+            Promise::new(payer.clone()).transfer(refund);
+        }
+        refund
+    }
+}
+
+// The rest of this file holds the inline tests for the code above
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, get_created_receipts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    fn get_context(attached_deposit: NearToken) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .predecessor_account_id(accounts(1))
+            .account_balance(NearToken::from_near(10))
+            .attached_deposit(attached_deposit);
+        builder
+    }
+
+    #[test]
+    fn test_charge_storage_refunds_excess() {
+        let context = get_context(NearToken::from_near(1));
+        testing_env!(context.build());
+        let initial_storage_usage = env::storage_usage();
+        env::storage_write(b"key", &[0u8; 100]);
+        let kept = ProposalContract::charge_storage(initial_storage_usage, &accounts(1));
+        let used_bytes = env::storage_usage() - initial_storage_usage;
+        assert_eq!(kept, env::storage_byte_cost().saturating_mul(used_bytes as u128));
+        assert_eq!(get_created_receipts().len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient deposit for storage")]
+    fn test_charge_storage_insufficient_deposit() {
+        let context = get_context(NearToken::from_yoctonear(1));
+        testing_env!(context.build());
+        let initial_storage_usage = env::storage_usage();
+        env::storage_write(b"key", &[0u8; 100]);
+        ProposalContract::charge_storage(initial_storage_usage, &accounts(1));
+    }
+}
@@ -1,54 +1,113 @@
-use near_sdk::{ env, near_bindgen, AccountId, ext_contract, Gas, NearToken, Promise, PromiseError};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{ env, near_bindgen, AccountId, ext_contract, NearToken, Promise, PromiseError};
 use near_sdk::json_types::U128;
+use serde::{Serialize, Deserialize};
 use crate::ProposalContract;
 use crate::ProposalState;
+use crate::VotingMode;
 use crate::ProposalContractExt;
+use crate::VotingWeight;
+use crate::storage::MIN_VOTE_DEPOSIT;
 
+#[allow(dead_code)]
 #[ext_contract(ft_contract)]
 pub trait FungibleToken {
     fn ft_balance_of(&self, account_id: AccountId) -> U128;
+    fn ft_total_supply(&self) -> U128;
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+}
+
+// What a voter can cast on a proposal
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub enum Ballot {
+    // Support for the option at this index
+    Option(u8),
+    // Counts toward the quorum without supporting any option
+    Abstain,
+    // Counts against every option, enough of them rejects the proposal and slashes the bond
+    NoWithVeto,
+}
+
+// Veto rule and proposer bond, the bond is held from creation until finalization
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct VetoConfig {
+    // Share of all ballots that has to be NoWithVeto for the proposal to be vetoed
+    pub threshold_percent: u8,
+    pub proposal_bond: NearToken,
+}
+
+impl Default for VetoConfig {
+    fn default() -> Self {
+        Self {
+            threshold_percent: 33,
+            proposal_bond: NearToken::from_near(0),
+        }
+    }
+}
+
+// Ballot counts returned by `get_ballot_counts`
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct BallotCounts {
+    pub options: Vec<(String, u64)>,
+    pub abstain: u64,
+    pub no_with_veto: u64,
 }
 
 #[near_bindgen]
 impl ProposalContract {
-    // Cast a vote on a specific proposal
+    // Cast a vote on a specific proposal, the attached deposit pays for the ballot storage
+    #[payable]
     pub fn vote(&mut self, proposal_id: u64, voter: AccountId, vote_option: u8) -> Promise {
-        let promise = ft_contract::ext(self.token_contract_id.clone())
-            .with_attached_deposit(NearToken::from_near(0))
-            .with_static_gas(Gas::from_tgas(5))
-            .ft_balance_of(voter.clone());
+        self.cast_ballot(proposal_id, voter, Ballot::Option(vote_option))
+    }
 
-        return promise.then(
+    // Cast any kind of ballot, `vote` is the shorthand for supporting an option
+    #[payable]
+    pub fn cast_ballot(&mut self, proposal_id: u64, voter: AccountId, ballot: Ballot) -> Promise {
+        self.assert_not_paused("vote");
+        // Accounts cast their own ballots, only the governance account may relay one on a voter's behalf
+        let caller = env::predecessor_account_id();
+        assert!(voter == caller || self.is_governance(&caller), "Only the voter can cast their ballot");
+        let deposit = env::attached_deposit();
+        assert!(deposit >= MIN_VOTE_DEPOSIT, "Attach at least {} to cover the vote storage", MIN_VOTE_DEPOSIT);
+        // Checked before the query so that a doomed ballot does not send its deposit through the callback
+        self.assert_can_vote(proposal_id, &voter, &ballot);
+        let proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        let sources = proposal.voting_weight.balance_sources();
+        if !sources.is_empty() {
+            return self.query_weighted_balances(sources, &voter).then(
+                Self::ext(env::current_account_id())
+                    .with_attached_deposit(deposit)
+                    .with_static_gas(self.gas_config.vote_callback)
+                    .with_unused_gas_weight(1)
+                    .process_weighted_vote_callback(proposal_id, voter, ballot)
+            );
+        }
+        let promise = self.query_voting_power(voter.clone());
+
+        // The callback does the storage work, so it receives the leftover prepaid gas
+        promise.then(
             Self::ext(env::current_account_id())
-                .with_static_gas(Gas::from_tgas(5))
-                .process_vote_callback(proposal_id, voter, vote_option)
+                .with_attached_deposit(deposit)
+                .with_static_gas(self.gas_config.vote_callback)
+                .with_unused_gas_weight(1)
+                .process_vote_callback(proposal_id, voter, ballot)
         )
     }
 
     #[private]
-    pub fn process_vote_callback(&mut self, proposal_id: u64, voter: AccountId, vote_option: u8, #[callback_result] balance: Result<U128, PromiseError>) {
+    pub fn process_vote_callback(&mut self, proposal_id: u64, voter: AccountId, ballot: Ballot, #[callback_result] balance: Result<U128, PromiseError>) {
         const MINIMUM_BALANCE_REQUIRED: u128 = 1;
         match balance {
             Ok(balance) => {
                 if balance.0 >= MINIMUM_BALANCE_REQUIRED {
-                    let mut proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
-
-                    assert!(env::block_timestamp() <= proposal.deadline, "Voting period has ended");
-                    assert!(proposal.state == ProposalState::Open, "Proposal is not open for voting");
-                    assert!(proposal.votes.get(&voter).is_none(), "Voter has already voted");
-                    assert!(proposal.options.get(vote_option as u64).is_some(), "Invalid option");
-                    
-                    //Register the vote
-                    proposal.votes.insert(&voter, &vote_option);
-                    self.proposals.insert(&proposal_id, &proposal);
-
-                    env::log_str(&format!("Vote cast by {} for option {}", voter, vote_option));
+                    self.record_ballot(proposal_id, voter, ballot, 0);
                 } else {
-                    env::panic_str("Insufficient balance to vote");
+                    Self::refund_deposit("Insufficient balance to vote");
                 }
             },
             Err(e) => {
-                env::panic_str(&format!("Failed to retrieve balance: {:?}", e));
+                Self::refund_deposit(&format!("Failed to retrieve balance: {:?}", e));
             }
         }
     }
@@ -56,41 +115,128 @@ impl ProposalContract {
     // Get the votes for a specific proposal
     pub fn get_votes(&self, proposal_id: u64) -> Vec<(String, u64)> {
         let proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
-        let mut votes: Vec<(String, u64)> = proposal.options.iter().map(|option| (option, 0)).collect();
-        for (_voter, vote_option) in proposal.votes.iter() {
-            let option_index = vote_option as usize;
-            if let Some((_option, count)) = votes.get_mut(option_index) {
-                *count += 1;
-                println!("Incrementing vote for option: {} to {}", _option, count);
-            }
-            println!("Final vote counts: {:?}", votes); // Debug output
+        proposal.options.iter().cloned().zip(proposal.tally.iter().copied()).collect()
+    }
+
+    // Get the option, abstain and veto counts of a proposal
+    pub fn get_ballot_counts(&self, proposal_id: u64) -> BallotCounts {
+        let proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        BallotCounts {
+            options: proposal.options.iter().cloned().zip(proposal.tally.iter().copied()).collect(),
+            abstain: proposal.abstain_count,
+            no_with_veto: proposal.veto_count,
         }
-        votes
+    }
+
+    // Get the ballot cast by a voter, if any
+    pub fn get_vote(&self, proposal_id: u64, voter: AccountId) -> Option<Ballot> {
+        self.votes.get(&(proposal_id, voter)).cloned()
+    }
+
+    pub fn set_veto_config(&mut self, veto_config: VetoConfig) {
+        self.assert_governance();
+        assert!(veto_config.threshold_percent <= 100, "Veto threshold cannot exceed 100 percent");
+        env::log_str(&format!("Veto config updated to {:?}", veto_config));
+        self.veto_config = veto_config;
+    }
+
+    pub fn get_veto_config(&self) -> VetoConfig {
+        self.veto_config.clone()
     }
 
     // Count votes and return status
     pub fn count_votes(&self, proposal_id: u64) -> ProposalState {
         let proposal = self.proposals.get(&proposal_id).expect("Proposal not found.");
-    
-        let mut votes_for_option_a = 0;
-        let mut votes_for_option_b = 0;
-    
-        // Iterate through votes to count for each option
-        for (_account, vote) in proposal.votes.iter() {
-            if vote == 0 { // Assuming 0 represents Option A
-                votes_for_option_a += 1;
-            } else if vote == 1 { // Assuming 1 represents Option B
-                votes_for_option_b += 1;
+        // Optimistic proposals pass unless objections escalated them to a vote
+        if proposal.voting_mode == VotingMode::Optimistic && proposal.escalated_at.is_none() {
+            return ProposalState::Passed;
+        }
+        // Reads the running tallies, so the cost does not grow with the number of votes
+        if proposal.is_vetoed(self.veto_config.threshold_percent) {
+            return ProposalState::Rejected;
+        }
+        match (&proposal.voting_mode, proposal.winning_option()) {
+            (VotingMode::Optimistic, Some(0)) => ProposalState::Passed,
+            (VotingMode::Optimistic, _) => ProposalState::Rejected,
+            (_, Some(_)) => ProposalState::Passed,
+            (_, None) => ProposalState::Rejected,
+        }
+    }
+}
+
+impl ProposalContract {
+    // Everything a ballot needs that is known before the voting power query
+    pub(crate) fn assert_can_vote(&self, proposal_id: u64, voter: &AccountId, ballot: &Ballot) {
+        let proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        assert!(proposal.state == ProposalState::Open, "Proposal is not open for voting");
+        assert!(env::block_timestamp() <= proposal.deadline, "Voting period has ended");
+        match proposal.voting_mode {
+            VotingMode::Open => {}
+            VotingMode::CommitReveal { .. } => env::panic_str("Proposal uses commit-reveal voting"),
+            VotingMode::Optimistic => assert!(
+                proposal.escalated_at.is_some(),
+                "Optimistic proposal has not been escalated to a vote"
+            ),
+        }
+        assert!(!self.votes.contains_key(&(proposal_id, voter.clone())), "Voter has already voted");
+        if let Ballot::Option(vote_option) = ballot {
+            assert!((*vote_option as usize) < proposal.options.len(), "Invalid option");
+        }
+    }
+
+    // Count an open ballot once the voter's weighted balance is known, the signer pays for its storage.
+    // `balance_weight` is 0 when only eligibility was checked. A ballot without weight refunds the deposit.
+    pub(crate) fn record_ballot(&mut self, proposal_id: u64, voter: AccountId, ballot: Ballot, balance_weight: u64) {
+        let initial_storage_usage = env::storage_usage();
+        // The proposal may have changed while the voting power was fetched
+        self.assert_can_vote(proposal_id, &voter, &ballot);
+        let proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+
+        let weight = match &proposal.voting_weight {
+            VotingWeight::OnePerVoter => 1,
+            VotingWeight::TokenWeighted { .. } => balance_weight,
+            VotingWeight::Reputation { .. } => balance_weight.saturating_add(self.get_reputation(voter.clone())),
+        };
+        if weight == 0 {
+            let reason = match proposal.voting_weight {
+                VotingWeight::Reputation { .. } => "No reputation to vote with",
+                _ => "Insufficient balance to vote",
+            };
+            return Self::refund_deposit(reason);
+        }
+
+        let payer = env::signer_account_id();
+        self.register_ballot(proposal_id, &voter, &payer, ballot.clone(), weight);
+        self.flush_collections();
+        Self::charge_storage(initial_storage_usage, &payer);
+
+        env::log_str(&format!("Vote cast by {} with weight {}: {:?}", voter, weight, ballot));
+    }
+
+    // Add a ballot to the tallies and the indexes, callers check the voting window first.
+    // `payer` is charged for the ballot storage and gets it back when the proposal is archived.
+    pub(crate) fn register_ballot(&mut self, proposal_id: u64, voter: &AccountId, payer: &AccountId, ballot: Ballot, weight: u64) {
+        let ballot_key = (proposal_id, voter.clone());
+        assert!(!self.votes.contains_key(&ballot_key), "Voter has already voted");
+        let proposal = self.proposals.get_mut(&proposal_id).expect("Proposal not found");
+        assert!(proposal.state == ProposalState::Open, "Proposal is not open for voting");
+
+        match ballot {
+            Ballot::Option(vote_option) => {
+                assert!((vote_option as usize) < proposal.options.len(), "Invalid option");
+                proposal.tally[vote_option as usize] += weight;
             }
+            Ballot::Abstain => proposal.abstain_count += weight,
+            Ballot::NoWithVeto => proposal.veto_count += weight,
         }
-    
-        // Determine the outcome based on vote counts and minimum votes requirement
-        if votes_for_option_a > votes_for_option_b && votes_for_option_a >= proposal.minimum_votes as u64 {
-            ProposalState::Passed
-        } else if votes_for_option_b > votes_for_option_a && votes_for_option_b >= proposal.minimum_votes as u64 {
-            ProposalState::Passed
-        } else {
-            ProposalState::Rejected
+        let voter_index = proposal.voter_count;
+        let earns_reputation = proposal.earns_reputation;
+        proposal.voter_count += 1;
+        self.votes.insert(ballot_key, ballot.clone());
+        self.voters.insert((proposal_id, voter_index), (voter.clone(), payer.clone()));
+        self.record_vote(voter, proposal_id, ballot, weight);
+        if earns_reputation {
+            self.award_reputation(voter, self.reputation_config.vote_reward);
         }
     }
 }
@@ -101,7 +247,10 @@ impl ProposalContract {
 mod tests {
     use super::*;
     // use near_sdk::MockedBlockchain;
-    use near_sdk::{testing_env, VMContext};
+    use near_sdk::{testing_env, Gas, VMContext};
+    use near_sdk::mock::MockAction;
+    use near_sdk::test_utils::get_created_receipts;
+    use crate::GasConfig;
     use near_sdk::{PublicKey};
     use std::str::FromStr;
 
@@ -110,13 +259,19 @@ mod tests {
     fn test_vote() {
         let context = get_context("voter".parse().unwrap());
         testing_env!(context);
-        let mut contract = ProposalContract::new("token-contract".parse().unwrap());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
         contract.create_proposal(
             "title".to_string(),
             "description".to_string(),
             1000,
             vec!["option1".to_string(), "option2".to_string()],
             2,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
         contract.vote(0, "voter".parse().unwrap(), 0);
     }
@@ -125,13 +280,19 @@ mod tests {
     fn test_count_votes() {
         let context = get_context("voter".parse().unwrap());
         testing_env!(context);
-        let mut contract = ProposalContract::new("token-contract".parse().unwrap());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
         contract.create_proposal(
             "title".to_string(),
             "description".to_string(),
             1000,
             vec!["option1".to_string(), "option2".to_string()],
             2,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
         contract.vote(0, "voter".parse().unwrap(), 0);
         assert_eq!(contract.count_votes(0), ProposalState::Rejected);
@@ -147,10 +308,10 @@ mod tests {
             input: vec![],
             block_index: 0,
             block_timestamp: 0,
-            account_balance: NearToken::from_yoctonear(0),
+            account_balance: NearToken::from_near(10),
             account_locked_balance: NearToken::from_yoctonear(0),
             storage_usage: 10u64.pow(6),
-            attached_deposit: NearToken::from_yoctonear(0),
+            attached_deposit: NearToken::from_near(1),
             prepaid_gas: Gas::from_tgas(200),
             random_seed: [0u8; 32],
             view_config: None,
@@ -159,35 +320,206 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_count_votes_any_option_can_win() {
+        let context = get_context("voter".parse().unwrap());
+        testing_env!(context);
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        contract.create_proposal(
+            "title".to_string(),
+            "description".to_string(),
+            1000,
+            vec!["option1".to_string(), "option2".to_string(), "option3".to_string()],
+            2,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        for (voter, vote_option) in [("alice", 2), ("bob", 2), ("carol", 0)] {
+            contract.process_vote_callback(0, voter.parse().unwrap(), Ballot::Option(vote_option), Ok(U128(1)));
+        }
+        assert_eq!(contract.count_votes(0), ProposalState::Passed);
+        assert_eq!(contract.get_votes(0)[2], ("option3".to_string(), 2));
+    }
+
+    #[test]
+    fn test_count_votes_tie_is_rejected() {
+        let context = get_context("voter".parse().unwrap());
+        testing_env!(context);
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        contract.create_proposal(
+            "title".to_string(),
+            "description".to_string(),
+            1000,
+            vec!["option1".to_string(), "option2".to_string(), "option3".to_string()],
+            1,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        for (voter, vote_option) in [("alice", 1), ("bob", 2)] {
+            contract.process_vote_callback(0, voter.parse().unwrap(), Ballot::Option(vote_option), Ok(U128(1)));
+        }
+        assert_eq!(contract.count_votes(0), ProposalState::Rejected);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the voter can cast their ballot")]
+    fn test_cast_ballot_for_another_account() {
+        let context = get_context("mallory".parse().unwrap());
+        testing_env!(context);
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        contract.create_proposal(
+            "title".to_string(),
+            "description".to_string(),
+            1000,
+            vec!["option1".to_string(), "option2".to_string()],
+            1,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        contract.cast_ballot(0, "alice".parse().unwrap(), Ballot::NoWithVeto);
+    }
+
+    fn create_bonded_proposal(contract: &mut ProposalContract, minimum_votes: u8) {
+        contract.set_veto_config(VetoConfig { threshold_percent: 33, proposal_bond: NearToken::from_millinear(500) });
+        contract.create_proposal(
+            "title".to_string(),
+            "description".to_string(),
+            1000,
+            vec!["option1".to_string(), "option2".to_string()],
+            minimum_votes,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+    }
+
+    fn transfers() -> Vec<NearToken> {
+        get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                MockAction::Transfer { deposit, .. } => Some(deposit),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_abstain_counts_toward_quorum_only() {
+        let mut context = get_context("proposal".parse().unwrap());
+        testing_env!(context.clone());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        create_bonded_proposal(&mut contract, 3);
+        contract.process_vote_callback(0, "alice".parse().unwrap(), Ballot::Option(1), Ok(U128(1)));
+        contract.process_vote_callback(0, "bob".parse().unwrap(), Ballot::Abstain, Ok(U128(1)));
+        assert_eq!(contract.count_votes(0), ProposalState::Rejected);
+        contract.process_vote_callback(0, "carol".parse().unwrap(), Ballot::Abstain, Ok(U128(1)));
+        assert_eq!(contract.count_votes(0), ProposalState::Passed);
+
+        let counts = contract.get_ballot_counts(0);
+        assert_eq!(counts.abstain, 2);
+        assert_eq!(counts.options[1], ("option2".to_string(), 1));
+
+        // The bond goes back to the proposer
+        context.block_timestamp = 1001;
+        testing_env!(context.clone());
+        contract.update_status(0);
+        assert!(transfers().contains(&NearToken::from_millinear(500)));
+
+        // Finalizing again does not refund the bond a second time
+        testing_env!(context);
+        contract.update_status(0);
+        assert!(transfers().is_empty());
+    }
+
+    #[test]
+    fn test_veto_rejects_and_slashes_bond() {
+        let mut context = get_context("proposal".parse().unwrap());
+        testing_env!(context.clone());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        create_bonded_proposal(&mut contract, 1);
+        contract.process_vote_callback(0, "alice".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
+        contract.process_vote_callback(0, "bob".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
+        contract.process_vote_callback(0, "carol".parse().unwrap(), Ballot::NoWithVeto, Ok(U128(1)));
+        assert_eq!(contract.get_vote(0, "carol".parse().unwrap()), Some(Ballot::NoWithVeto));
+
+        context.block_timestamp = 1001;
+        testing_env!(context);
+        assert_eq!(contract.update_status(0), ProposalState::Rejected);
+        assert!(!transfers().contains(&NearToken::from_millinear(500)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient deposit for storage")]
+    fn test_create_proposal_without_bond() {
+        let mut context = get_context("proposal".parse().unwrap());
+        context.attached_deposit = NearToken::from_millinear(500);
+        testing_env!(context);
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        create_bonded_proposal(&mut contract, 1);
+    }
+
     #[test]
     fn test_process_vote_callback() {
         let context = get_context("voter".parse().unwrap());
         testing_env!(context);
-        let mut contract = ProposalContract::new("token-contract".parse().unwrap());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
         contract.create_proposal(
             "title".to_string(),
             "description".to_string(),
             1000,
             vec!["option1".to_string(), "option2".to_string()],
             2,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
-        contract.process_vote_callback(0, "voter".parse().unwrap(), 0, Ok(U128(1)));
+        contract.process_vote_callback(0, "voter".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
     }
 
     #[test]
-    #[should_panic(expected = "Insufficient balance to vote")]
     fn test_process_vote_callback_insufficient_balance() {
         let context = get_context("voter".parse().unwrap());
         testing_env!(context);
-        let mut contract = ProposalContract::new("token-contract".parse().unwrap());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
         contract.create_proposal(
             "title".to_string(),
             "description".to_string(),
             1000,
             vec!["option1".to_string(), "option2".to_string()],
             2,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
-        contract.process_vote_callback(0, "voter".parse().unwrap(), 0, Ok(U128(0)));
+        contract.process_vote_callback(0, "voter".parse().unwrap(), Ballot::Option(0), Ok(U128(0)));
+        // The deposit goes back to the voter instead of staying with the contract
+        assert_eq!(contract.get_vote(0, "voter".parse().unwrap()), None);
+        assert!(get_created_receipts().iter().flat_map(|receipt| receipt.actions.iter()).any(|action| matches!(
+            action,
+            MockAction::Transfer { deposit, .. } if *deposit == NearToken::from_near(1)
+        )));
     }
 
 /*     #[test]
@@ -195,7 +527,7 @@ mod tests {
     fn test_process_vote_callback_voting_period_ended() {
         let context = get_context("voter".parse().unwrap());
         testing_env!(context);
-        let mut contract = ProposalContract::new("token-contract".parse().unwrap());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
         contract.create_proposal(
             "title".to_string(),
             "description".to_string(),
@@ -211,7 +543,7 @@ mod tests {
     fn test_process_vote_callback_proposal_not_open() {
         let context = get_context("voter".parse().unwrap());
         testing_env!(context);
-        let mut contract = ProposalContract::new("token-contract".parse().unwrap());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
         contract.create_proposal(
             "title".to_string(),
             "description".to_string(),
@@ -228,16 +560,22 @@ mod tests {
     fn test_process_vote_callback_voter_already_voted() {
         let context = get_context("voter".parse().unwrap());
         testing_env!(context);
-        let mut contract = ProposalContract::new("token-contract".parse().unwrap());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
         let proposal_id = contract.create_proposal(
             "title".to_string(),
             "description".to_string(),
             1000,
             vec!["option1".to_string(), "option2".to_string()],
             2,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
-        contract.process_vote_callback(proposal_id, "voter".parse().unwrap(), 0, Ok(U128(1)));
-        contract.process_vote_callback(0, "voter".parse().unwrap(), 0, Ok(U128(1)));
+        contract.process_vote_callback(proposal_id, "voter".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
+        contract.process_vote_callback(0, "voter".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
     }
 
     #[test]
@@ -245,31 +583,47 @@ mod tests {
     fn test_process_vote_callback_invalid_option() {
         let context = get_context("voter".parse().unwrap());
         testing_env!(context);
-        let mut contract = ProposalContract::new("token-contract".parse().unwrap());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
         contract.create_proposal(
             "title".to_string(),
             "description".to_string(),
             1000,
             vec!["option1".to_string(), "option2".to_string()],
             2,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
-        contract.process_vote_callback(0, "voter".parse().unwrap(), 2, Ok(U128(1)));
+        contract.process_vote_callback(0, "voter".parse().unwrap(), Ballot::Option(2), Ok(U128(1)));
     }
 
     #[test]
-    #[should_panic(expected = "Failed to retrieve balance: Failed")]
     fn test_process_vote_callback_not_enough_allowance() {
         let context = get_context("voter".parse().unwrap());
         testing_env!(context);
-        let mut contract = ProposalContract::new("token-contract".parse().unwrap());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
         contract.create_proposal(
             "title".to_string(),
             "description".to_string(),
             1000,
             vec!["option1".to_string(), "option2".to_string()],
             2,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
-        contract.process_vote_callback(0, "voter".parse().unwrap(), 0, Err(near_sdk::PromiseError::Failed));
+        contract.process_vote_callback(0, "voter".parse().unwrap(), Ballot::Option(0), Err(near_sdk::PromiseError::Failed));
+        assert_eq!(contract.get_vote(0, "voter".parse().unwrap()), None);
+        assert!(get_created_receipts().iter().flat_map(|receipt| receipt.actions.iter()).any(|action| matches!(
+            action,
+            MockAction::Transfer { deposit, .. } if *deposit == NearToken::from_near(1)
+        )));
     }
 
     #[test]
@@ -277,27 +631,138 @@ mod tests {
     fn test_process_vote_callback_proposal_not_found() {
         let context = get_context("voter".parse().unwrap());
         testing_env!(context);
-        let mut contract = ProposalContract::new("token-contract".parse().unwrap());
-        contract.process_vote_callback(0, "voter".parse().unwrap(), 0, Ok(U128(1)));
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        contract.process_vote_callback(0, "voter".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "to cover the vote storage")]
+    fn test_vote_without_deposit() {
+        let mut context = get_context("voter".parse().unwrap());
+        testing_env!(context.clone());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        contract.create_proposal(
+            "title".to_string(),
+            "description".to_string(),
+            1000,
+            vec!["option1".to_string(), "option2".to_string()],
+            2,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        context.attached_deposit = NearToken::from_yoctonear(0);
+        testing_env!(context);
+        contract.vote(0, "voter".parse().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_vote_uses_configured_gas() {
+        let context = get_context("proposal".parse().unwrap());
+        testing_env!(context);
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        contract.set_gas_config(GasConfig { ft_balance_of: Gas::from_tgas(7), vote_callback: Gas::from_tgas(30), ..Default::default() });
+        contract.create_proposal(
+            "title".to_string(),
+            "description".to_string(),
+            1000,
+            vec!["option1".to_string(), "option2".to_string()],
+            1,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        contract.vote(0, "voter".parse().unwrap(), 0);
+
+        let calls: Vec<(Gas, u64)> = get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                MockAction::FunctionCallWeight { prepaid_gas, gas_weight, .. } => Some((prepaid_gas, gas_weight.0)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(calls, vec![(Gas::from_tgas(7), 0), (Gas::from_tgas(30), 1)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient deposit for storage")]
+    fn test_process_vote_callback_insufficient_deposit() {
+        let mut context = get_context("voter".parse().unwrap());
+        testing_env!(context.clone());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        contract.create_proposal(
+            "title".to_string(),
+            "description".to_string(),
+            1000,
+            vec!["option1".to_string(), "option2".to_string()],
+            2,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        context.attached_deposit = NearToken::from_yoctonear(1);
+        testing_env!(context);
+        contract.process_vote_callback(0, "voter".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
+    }
+
+    #[test]
+    fn test_votes_are_scoped_by_proposal() {
+        let context = get_context("voter".parse().unwrap());
+        testing_env!(context);
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        for _ in 0..2 {
+            contract.create_proposal(
+                "title".to_string(),
+                "description".to_string(),
+                1000,
+                vec!["option1".to_string(), "option2".to_string()],
+                1,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            );
+        }
+        contract.process_vote_callback(0, "voter".parse().unwrap(), Ballot::Option(0), Ok(U128(1)));
+        contract.process_vote_callback(1, "voter".parse().unwrap(), Ballot::Option(1), Ok(U128(1)));
+        assert_eq!(contract.get_vote(0, "voter".parse().unwrap()), Some(Ballot::Option(0)));
+        assert_eq!(contract.get_vote(1, "voter".parse().unwrap()), Some(Ballot::Option(1)));
+        assert_eq!(contract.get_votes(1), vec![("option1".to_string(), 0), ("option2".to_string(), 1)]);
     }
 
     #[test]
     fn test_get_votes() {
         let context = get_context("voter".parse().unwrap());
         testing_env!(context);
-        let mut contract = ProposalContract::new("token-contract".parse().unwrap());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), None);
         let proposal_id = contract.create_proposal(
             "title".to_string(),
             "description".to_string(),
             1000,
             vec!["option1".to_string(), "option2".to_string()],
             2,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
-        let voter = "voter".parse().unwrap();
         let vote_option: u8 = 0; // Assuming option 0 is a valid option.
-        let mut proposal = contract.proposals.get(&proposal_id).expect("Proposal not found");
-        proposal.votes.insert(&voter, &vote_option);
-        contract.proposals.insert(&proposal_id, &proposal);
+        contract.process_vote_callback(proposal_id, "voter".parse().unwrap(), Ballot::Option(vote_option), Ok(U128(1)));
+        assert_eq!(contract.get_vote(proposal_id, "voter".parse().unwrap()), Some(Ballot::Option(vote_option)));
         assert_eq!(contract.get_votes(0), vec![("option1".to_string(), 1), ("option2".to_string(), 0)]);
     }
 }
\ No newline at end of file
@@ -0,0 +1,333 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::{env, ext_contract, near_bindgen, serde_json, AccountId, Promise, PromiseResult};
+use serde::{Deserialize, Serialize};
+use crate::vote::{ft_contract, Ballot};
+use crate::{ProposalContract, ProposalContractExt};
+
+const MAX_WEIGHTED_SOURCES: usize = 5;
+
+#[allow(dead_code)]
+#[ext_contract(nft_contract)]
+pub trait NonFungibleToken {
+    fn nft_supply_for_owner(&self, account_id: AccountId) -> U128;
+}
+
+#[allow(dead_code)]
+#[ext_contract(dao_contract)]
+pub trait MembershipRegistry {
+    fn get_member_voting_power(&self, account_id: AccountId) -> U128;
+}
+
+// Where the contract looks up whether an account can vote and comment
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub enum VotingPowerSource {
+    // Holders of the token contract
+    #[default]
+    FtBalance,
+    // Number of NEP-171 tokens held on this contract
+    NftCount { nft_contract_id: AccountId },
+    // Active members of the DAO's member registry
+    Membership { dao_contract_id: AccountId },
+}
+
+// How much a ballot counts on a proposal
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub enum VotingWeight {
+    // Every eligible account casts one vote, eligibility comes from the contract's voting power source
+    #[default]
+    OnePerVoter,
+    // Balances on each token are queried in parallel and summed with their multipliers.
+    // Balances are read live and not locked, so tokens moved to another account after a ballot
+    // count again when that account votes. Only use it where this is acceptable.
+    TokenWeighted { sources: Vec<WeightedSource> },
+    // Decayed reputation of the voter, plus the weighted balance on `balance` when set
+    Reputation { balance: Option<WeightedSource> },
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct WeightedSource {
+    pub token_contract_id: AccountId,
+    // Balances are counted in whole tokens
+    pub decimals: u8,
+    pub multiplier: u32,
+}
+
+impl WeightedSource {
+    fn weigh(&self, balance: u128) -> u64 {
+        let whole_tokens = balance / 10u128.pow(self.decimals as u32);
+        u64::try_from(whole_tokens.saturating_mul(self.multiplier as u128)).unwrap_or(u64::MAX)
+    }
+}
+
+impl VotingWeight {
+    // Tokens whose balances are queried when a ballot is cast, in the order the callback reads them
+    pub(crate) fn balance_sources(&self) -> &[WeightedSource] {
+        match self {
+            VotingWeight::OnePerVoter => &[],
+            VotingWeight::TokenWeighted { sources } => sources,
+            VotingWeight::Reputation { balance } => balance.as_slice(),
+        }
+    }
+
+    pub(crate) fn assert_valid(&self) {
+        if let VotingWeight::TokenWeighted { sources } = self {
+            assert!(
+                !sources.is_empty() && sources.len() <= MAX_WEIGHTED_SOURCES,
+                "Token weighted voting needs between 1 and {} sources",
+                MAX_WEIGHTED_SOURCES
+            );
+        }
+        let sources = self.balance_sources();
+        for (index, source) in sources.iter().enumerate() {
+            assert!(source.multiplier > 0, "Multipliers must be positive");
+            assert!(source.decimals <= 38, "Decimals cannot exceed 38");
+            assert!(
+                !sources[..index].iter().any(|other| other.token_contract_id == source.token_contract_id),
+                "Duplicate voting power source: {}",
+                source.token_contract_id
+            );
+        }
+    }
+}
+
+#[near_bindgen]
+impl ProposalContract {
+    // Sum the weighted balances returned by the joined `ft_balance_of` calls, in source order
+    #[private]
+    pub fn process_weighted_vote_callback(&mut self, proposal_id: u64, voter: AccountId, ballot: Ballot) {
+        let proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        let sources = proposal.voting_weight.balance_sources();
+        assert_eq!(env::promise_results_count(), sources.len() as u64, "Expected one balance per source");
+        let mut weight = 0u64;
+        for (index, source) in sources.iter().enumerate() {
+            let balance = match env::promise_result(index as u64) {
+                PromiseResult::Successful(data) => serde_json::from_slice::<U128>(&data).ok(),
+                PromiseResult::Failed => None,
+            };
+            match balance {
+                Some(balance) => weight = weight.saturating_add(source.weigh(balance.0)),
+                None => {
+                    return Self::refund_deposit(&format!("Failed to retrieve balance from {}", source.token_contract_id))
+                }
+            }
+        }
+        self.record_ballot(proposal_id, voter, ballot, weight);
+    }
+
+    // Weighted ballots count the balances held when they were cast, tokens are not locked and
+    // can be counted again from another account
+    pub fn get_voting_weight(&self, proposal_id: u64) -> VotingWeight {
+        self.proposals.get(&proposal_id).expect("Proposal not found").voting_weight.clone()
+    }
+
+    pub fn set_voting_power_source(&mut self, source: VotingPowerSource) {
+        self.assert_governance();
+        env::log_str(&format!("Voting power source set to {:?}", source));
+        self.voting_power_source = source;
+    }
+
+    pub fn get_voting_power_source(&self) -> VotingPowerSource {
+        self.voting_power_source.clone()
+    }
+}
+
+impl ProposalContract {
+    // Query the voting power of an account, every source resolves to a U128 for the callbacks
+    pub(crate) fn query_voting_power(&self, account_id: AccountId) -> Promise {
+        match &self.voting_power_source {
+            VotingPowerSource::FtBalance => ft_contract::ext(self.token_contract_id.clone())
+                .with_static_gas(self.gas_config.ft_balance_of)
+                .with_unused_gas_weight(0)
+                .ft_balance_of(account_id),
+            VotingPowerSource::NftCount { nft_contract_id } => nft_contract::ext(nft_contract_id.clone())
+                .with_static_gas(self.gas_config.ft_balance_of)
+                .with_unused_gas_weight(0)
+                .nft_supply_for_owner(account_id),
+            VotingPowerSource::Membership { dao_contract_id } => dao_contract::ext(dao_contract_id.clone())
+                .with_static_gas(self.gas_config.ft_balance_of)
+                .with_unused_gas_weight(0)
+                .get_member_voting_power(account_id),
+        }
+    }
+
+    // One `ft_balance_of` per source, joined so they run in parallel
+    pub(crate) fn query_weighted_balances(&self, sources: &[WeightedSource], account_id: &AccountId) -> Promise {
+        sources
+            .iter()
+            .map(|source| {
+                ft_contract::ext(source.token_contract_id.clone())
+                    .with_static_gas(self.gas_config.ft_balance_of)
+                    .with_unused_gas_weight(0)
+                    .ft_balance_of(account_id.clone())
+            })
+            .reduce(|joined, promise| joined.and(promise))
+            .expect("No voting power sources")
+    }
+}
+
+// The rest of this file holds the inline tests for the code above
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::mock::MockAction;
+    use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+    use near_sdk::{test_vm_config, testing_env, NearToken, RuntimeFeesConfig};
+
+    const ONE_TOKEN: u128 = 10u128.pow(24);
+
+    fn queried_contracts() -> Vec<(String, String)> {
+        get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| {
+                let receiver_id = receipt.receiver_id.to_string();
+                receipt.actions.into_iter().filter_map(move |action| match action {
+                    MockAction::FunctionCallWeight { method_name, .. } => {
+                        Some((receiver_id.clone(), String::from_utf8(method_name).unwrap()))
+                    }
+                    _ => None,
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_membership_source_queries_dao() {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("proposal".parse().unwrap())
+            .predecessor_account_id("alice".parse().unwrap())
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), Some("alice".parse().unwrap()));
+        contract.set_voting_power_source(VotingPowerSource::Membership { dao_contract_id: "dao".parse().unwrap() });
+        contract.create_proposal(
+            "title".to_string(),
+            "description".to_string(),
+            1000,
+            vec!["option1".to_string(), "option2".to_string()],
+            1,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        contract.vote(0, "alice".parse().unwrap(), 0);
+        assert_eq!(queried_contracts()[0], ("dao".to_string(), "get_member_voting_power".to_string()));
+    }
+
+    #[test]
+    fn test_nft_source_queries_supply_for_owner() {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("proposal".parse().unwrap())
+            .predecessor_account_id("alice".parse().unwrap())
+            .build());
+        let mut contract = ProposalContract::new("token-contract".parse().unwrap(), Some("alice".parse().unwrap()));
+        contract.set_voting_power_source(VotingPowerSource::NftCount { nft_contract_id: "nft".parse().unwrap() });
+        contract.query_voting_power("alice".parse().unwrap());
+        assert_eq!(queried_contracts()[0], ("nft".to_string(), "nft_supply_for_owner".to_string()));
+    }
+
+    fn weighted_sources() -> VotingWeight {
+        VotingWeight::TokenWeighted {
+            sources: vec![
+                WeightedSource { token_contract_id: "fdao".parse().unwrap(), decimals: 24, multiplier: 1 },
+                WeightedSource { token_contract_id: "lp-token".parse().unwrap(), decimals: 24, multiplier: 3 },
+            ],
+        }
+    }
+
+    fn setup_weighted_proposal() -> ProposalContract {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("proposal".parse().unwrap())
+            .predecessor_account_id("alice".parse().unwrap())
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let mut contract = ProposalContract::new("fdao".parse().unwrap(), None);
+        contract.create_proposal(
+            "title".to_string(),
+            "description".to_string(),
+            1000,
+            vec!["option1".to_string(), "option2".to_string()],
+            1,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(weighted_sources()),
+        );
+        contract
+    }
+
+    fn with_balances(balances: &[u128]) {
+        let results = balances
+            .iter()
+            .map(|balance| PromiseResult::Successful(serde_json::to_vec(&U128(*balance)).unwrap()))
+            .collect();
+        testing_env!(
+            VMContextBuilder::new()
+                .current_account_id("proposal".parse().unwrap())
+                .predecessor_account_id("proposal".parse().unwrap())
+                .attached_deposit(NearToken::from_near(1))
+                .build(),
+            test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            results,
+        );
+    }
+
+    #[test]
+    fn test_weighted_vote_queries_every_source() {
+        let mut contract = setup_weighted_proposal();
+        contract.vote(0, "alice".parse().unwrap(), 0);
+        let queried = queried_contracts();
+        assert_eq!(queried[0], ("fdao".to_string(), "ft_balance_of".to_string()));
+        assert_eq!(queried[1], ("lp-token".to_string(), "ft_balance_of".to_string()));
+        assert_eq!(queried[2], ("proposal".to_string(), "process_weighted_vote_callback".to_string()));
+    }
+
+    #[test]
+    fn test_weighted_callback_sums_balances() {
+        let mut contract = setup_weighted_proposal();
+        with_balances(&[5 * ONE_TOKEN, 2 * ONE_TOKEN]);
+        contract.process_weighted_vote_callback(0, "alice".parse().unwrap(), Ballot::Option(1));
+        assert_eq!(contract.get_votes(0)[1], ("option2".to_string(), 11));
+        assert_eq!(contract.get_votes_by_account("alice".parse().unwrap(), None, None)[0].weight, 11);
+    }
+
+    #[test]
+    fn test_weighted_callback_below_one_token() {
+        let mut contract = setup_weighted_proposal();
+        with_balances(&[ONE_TOKEN / 2, 0]);
+        contract.process_weighted_vote_callback(0, "alice".parse().unwrap(), Ballot::Option(0));
+        assert_eq!(contract.get_vote(0, "alice".parse().unwrap()), None);
+        assert!(get_created_receipts().iter().flat_map(|receipt| receipt.actions.iter()).any(|action| matches!(
+            action,
+            MockAction::Transfer { deposit, .. } if *deposit == NearToken::from_near(1)
+        )));
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate voting power source: fdao")]
+    fn test_duplicate_weighted_source() {
+        let source = WeightedSource { token_contract_id: "fdao".parse().unwrap(), decimals: 24, multiplier: 1 };
+        VotingWeight::TokenWeighted { sources: vec![source.clone(), source] }.assert_valid();
+    }
+
+    #[test]
+    fn test_default_source_queries_token() {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("proposal".parse().unwrap())
+            .predecessor_account_id("alice".parse().unwrap())
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        let contract = ProposalContract::new("token-contract".parse().unwrap(), None);
+        assert_eq!(contract.get_voting_power_source(), VotingPowerSource::FtBalance);
+        contract.query_voting_power("alice".parse().unwrap());
+        assert_eq!(queried_contracts()[0], ("token-contract".to_string(), "ft_balance_of".to_string()));
+    }
+}
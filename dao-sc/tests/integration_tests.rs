@@ -65,6 +65,7 @@ async fn test_create_proposal_and_vote() -> anyhow::Result<()> {
             "options_vec": ["Yes", "No"],
             "minimum_votes": 1
         }))
+        .deposit(NearToken::from_near(1))
         .max_gas()
         .transact()
         .await?;
@@ -130,6 +131,7 @@ async fn test_create_proposal_and_vote() -> anyhow::Result<()> {
             "voter": voter_account.id(),
             "vote_option": 0
         }))
+        .deposit(NearToken::from_millinear(10))
         .max_gas()
         .transact()
         .await?;
@@ -151,3 +153,879 @@ async fn test_create_proposal_and_vote() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_factory_creates_two_daos() -> anyhow::Result<()> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    // Compile the factory and the contracts it deploys
+    let factory_wasm = near_workspaces::compile_project("./dao-factory").await?;
+    let dao_contract_wasm = near_workspaces::compile_project("./dao-contract").await?;
+    let proposal_contract_wasm = near_workspaces::compile_project("./proposal-contract").await?;
+    let token_contract_wasm = near_workspaces::compile_project("./token-contract").await?;
+
+    let factory_contract = sandbox.dev_deploy(&factory_wasm).await?;
+    let token_contract = sandbox.dev_deploy(&token_contract_wasm).await?;
+    let owner_account = sandbox.dev_create_account().await?;
+    let admin_account = sandbox.dev_create_account().await?;
+
+    let _token_contract_result = token_contract
+        .call("new_default_meta")
+        .args_json(json!({
+            "owner_id": owner_account.id(),
+            "total_supply": "1000000000000000000000000"
+        }))
+        .transact()
+        .await?;
+
+    let res_init = factory_contract
+        .call("new")
+        .args_json(json!({
+            "owner_id": owner_account.id(),
+            "default_token_contract_id": token_contract.id()
+        }))
+        .transact()
+        .await?;
+    assert!(res_init.is_success(), "Factory initialization failed");
+
+    // Upload the contract code the factory deploys
+    let res_dao_code = owner_account
+        .call(factory_contract.id(), "set_dao_code")
+        .args(dao_contract_wasm)
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res_dao_code.is_success(), "Storing the DAO code failed");
+
+    let res_proposal_code = owner_account
+        .call(factory_contract.id(), "set_proposal_code")
+        .args(proposal_contract_wasm)
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res_proposal_code.is_success(), "Storing the proposal code failed");
+
+    // One DAO shares the FDAO token, the other brings its own token account
+    for (name, token_id) in [("ops", None), ("grants", Some(owner_account.id().clone()))] {
+        let res_create = admin_account
+            .call(factory_contract.id(), "create_dao")
+            .args_json(json!({
+                "name": name,
+                "admin_account_id": admin_account.id(),
+                "token_contract_id": token_id
+            }))
+            .deposit(NearToken::from_near(20))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(res_create.is_success(), "Creating DAO {} failed", name);
+        let created: bool = res_create.json()?;
+        assert!(created, "DAO {} was not recorded", name);
+    }
+
+    let daos: Vec<serde_json::Value> = factory_contract
+        .call("get_daos")
+        .args_json(json!({ "from_index": 0, "limit": 10 }))
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(daos.len(), 2, "The factory should track two DAOs");
+    assert_eq!(daos[0]["token_contract_id"], json!(token_contract.id()));
+    assert_eq!(daos[1]["token_contract_id"], json!(owner_account.id()));
+
+    // Each deployed DAO points at its own proposal contract
+    for dao in daos.iter() {
+        let dao_account_id: near_workspaces::AccountId = dao["dao_account_id"].as_str().unwrap().parse()?;
+        let proposal_contract_id: String = sandbox
+            .view(&dao_account_id, "get_proposal_contract_id")
+            .await?
+            .json()?;
+        assert_eq!(json!(proposal_contract_id), dao["proposal_contract_id"]);
+    }
+
+    let second_page: Vec<serde_json::Value> = factory_contract
+        .call("get_daos")
+        .args_json(json!({ "from_index": 1, "limit": 10 }))
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(second_page.len(), 1);
+    assert_eq!(second_page[0]["name"], "grants");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_vote_gas_stays_flat() -> anyhow::Result<()> {
+    const VOTERS: usize = 200;
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let proposal_contract_wasm = near_workspaces::compile_project("./proposal-contract").await?;
+    let token_contract_wasm = near_workspaces::compile_project("./token-contract").await?;
+    let proposal_contract = sandbox.dev_deploy(&proposal_contract_wasm).await?;
+    let token_contract = sandbox.dev_deploy(&token_contract_wasm).await?;
+    let root_account = sandbox.root_account()?;
+
+    let _token_contract_result = token_contract
+        .call("new_default_meta")
+        .args_json(json!({
+            "owner_id": root_account.id(),
+            "total_supply": "1000000000000000000000000"
+        }))
+        .transact()
+        .await?;
+    let _proposal_contract_result = proposal_contract
+        .call("new")
+        .args_json(json!({ "token_contract_id": token_contract.id() }))
+        .transact()
+        .await?;
+
+    // Keep the proposal open while every voter casts a ballot
+    let now = sandbox.view_block().await?.timestamp();
+    let deadline = now + 30 * 60 * 1_000_000_000;
+    let create_proposal_outcome = root_account
+        .call(proposal_contract.id(), "create_proposal")
+        .args_json(json!({
+            "title": "Gas benchmark",
+            "description": "Hundreds of ballots",
+            "deadline": deadline,
+            "options_vec": ["Yes", "No", "Abstain"],
+            "minimum_votes": 1
+        }))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    let proposal_id: u64 = create_proposal_outcome.json()?;
+
+    let mut vote_gas = Vec::with_capacity(VOTERS);
+    for index in 0..VOTERS {
+        let voter_account = root_account
+            .create_subaccount(&format!("voter{:03}", index))
+            .initial_balance(NearToken::from_near(1))
+            .transact()
+            .await?
+            .into_result()?;
+        root_account
+            .call(token_contract.id(), "storage_deposit")
+            .args_json(json!({ "account_id": voter_account.id(), "registration_only": true }))
+            .deposit(NearToken::from_millinear(10))
+            .transact()
+            .await?
+            .into_result()?;
+        root_account
+            .call(token_contract.id(), "ft_transfer")
+            .args_json(json!({ "receiver_id": voter_account.id(), "amount": "1000" }))
+            .deposit(NearToken::from_yoctonear(1))
+            .transact()
+            .await?
+            .into_result()?;
+
+        let res_vote = voter_account
+            .call(proposal_contract.id(), "vote")
+            .args_json(json!({
+                "proposal_id": proposal_id,
+                "voter": voter_account.id(),
+                "vote_option": index % 3
+            }))
+            .deposit(NearToken::from_millinear(10))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(res_vote.is_success(), "Vote {} failed", index);
+        vote_gas.push(res_vote.total_gas_burnt.as_gas());
+    }
+
+    // The last ballot costs about the same as the first one
+    let first_vote_gas = vote_gas[0];
+    let last_vote_gas = vote_gas[VOTERS - 1];
+    assert!(
+        last_vote_gas <= first_vote_gas + first_vote_gas / 10,
+        "Vote gas grew from {} to {}",
+        first_vote_gas,
+        last_vote_gas
+    );
+
+    let votes: Vec<(String, u64)> = proposal_contract
+        .call("get_votes")
+        .args_json(json!({ "proposal_id": proposal_id }))
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(votes.iter().map(|(_, count)| count).sum::<u64>(), VOTERS as u64);
+
+    // Finalization reads the tallies instead of the ballots
+    while sandbox.view_block().await?.timestamp() <= deadline {
+        sandbox.fast_forward(1_000).await?;
+    }
+    let res_finalize = root_account
+        .call(proposal_contract.id(), "update_status")
+        .args_json(json!({ "proposal_id": proposal_id }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res_finalize.is_success(), "Finalization failed");
+    assert!(
+        res_finalize.total_gas_burnt.as_gas() < first_vote_gas,
+        "Finalization used {} gas",
+        res_finalize.total_gas_burnt.as_gas()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_finalize_proposal_through_dao() -> anyhow::Result<()> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let dao_contract_wasm = near_workspaces::compile_project("./dao-contract").await?;
+    let proposal_contract_wasm = near_workspaces::compile_project("./proposal-contract").await?;
+    let token_contract_wasm = near_workspaces::compile_project("./token-contract").await?;
+    let dao_contract = sandbox.dev_deploy(&dao_contract_wasm).await?;
+    let proposal_contract = sandbox.dev_deploy(&proposal_contract_wasm).await?;
+    let token_contract = sandbox.dev_deploy(&token_contract_wasm).await?;
+    let root_account = sandbox.dev_create_account().await?;
+
+    let _token_contract_result = token_contract
+        .call("new_default_meta")
+        .args_json(json!({
+            "owner_id": root_account.id(),
+            "total_supply": "1000000000000000000000000"
+        }))
+        .transact()
+        .await?;
+    let _proposal_contract_result = proposal_contract
+        .call("new")
+        .args_json(json!({ "token_contract_id": token_contract.id() }))
+        .transact()
+        .await?;
+    let _dao_contract_result = dao_contract
+        .call("new")
+        .args_json(json!({
+            "admin_account_id": root_account.id(),
+            "proposal_contract_id": proposal_contract.id()
+        }))
+        .transact()
+        .await?;
+
+    let now = sandbox.view_block().await?.timestamp();
+    let deadline = now + 60 * 1_000_000_000;
+    let create_proposal_outcome = root_account
+        .call(dao_contract.id(), "create_proposal")
+        .args_json(json!({
+            "title": "Proposal 1",
+            "description": "Description here",
+            "deadline": deadline,
+            "options_vec": ["Yes", "No"],
+            "minimum_votes": 1
+        }))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    let proposal_id: u64 = create_proposal_outcome.json()?;
+
+    let res_vote = root_account
+        .call(proposal_contract.id(), "vote")
+        .args_json(json!({
+            "proposal_id": proposal_id,
+            "voter": root_account.id(),
+            "vote_option": 0
+        }))
+        .deposit(NearToken::from_millinear(10))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res_vote.is_success(), "Vote failed");
+
+    while sandbox.view_block().await?.timestamp() <= deadline {
+        sandbox.fast_forward(1_000).await?;
+    }
+
+    // The DAO returns and records the state the proposal contract settled on
+    let res_finalize = root_account
+        .call(dao_contract.id(), "finalize_proposal")
+        .args_json(json!({ "proposal_id": proposal_id }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res_finalize.is_success(), "Finalization failed");
+    let final_state: String = res_finalize.json()?;
+    assert_eq!(final_state, "Passed");
+
+    let recorded_state: Option<String> = dao_contract
+        .call("get_finalized_state")
+        .args_json(json!({ "proposal_id": proposal_id }))
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(recorded_state.as_deref(), Some("Passed"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_large_proposal_with_configured_gas() -> anyhow::Result<()> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let dao_contract_wasm = near_workspaces::compile_project("./dao-contract").await?;
+    let proposal_contract_wasm = near_workspaces::compile_project("./proposal-contract").await?;
+    let token_contract_wasm = near_workspaces::compile_project("./token-contract").await?;
+    let dao_contract = sandbox.dev_deploy(&dao_contract_wasm).await?;
+    let proposal_contract = sandbox.dev_deploy(&proposal_contract_wasm).await?;
+    let token_contract = sandbox.dev_deploy(&token_contract_wasm).await?;
+    let root_account = sandbox.dev_create_account().await?;
+
+    let _token_contract_result = token_contract
+        .call("new_default_meta")
+        .args_json(json!({
+            "owner_id": root_account.id(),
+            "total_supply": "1000000000000000000000000"
+        }))
+        .transact()
+        .await?;
+    let _proposal_contract_result = proposal_contract
+        .call("new")
+        .args_json(json!({ "token_contract_id": token_contract.id() }))
+        .transact()
+        .await?;
+    let _dao_contract_result = dao_contract
+        .call("new")
+        .args_json(json!({
+            "admin_account_id": root_account.id(),
+            "proposal_contract_id": proposal_contract.id()
+        }))
+        .transact()
+        .await?;
+
+    // Raise the gas budgets through the governed setters
+    let res_dao_gas = root_account
+        .call(dao_contract.id(), "set_gas_config")
+        .args_json(json!({
+            "gas_config": {
+                "create_proposal": "60000000000000",
+                "create_proposal_callback": "10000000000000",
+                "finalize_proposal": "30000000000000",
+                "finalize_proposal_callback": "10000000000000"
+            }
+        }))
+        .transact()
+        .await?;
+    assert!(res_dao_gas.is_success(), "Updating the DAO gas config failed");
+    let res_proposal_gas = proposal_contract
+        .call("set_gas_config")
+        .args_json(json!({
+            "gas_config": {
+                "ft_balance_of": "5000000000000",
+                "vote_callback": "40000000000000",
+                "comment_callback": "10000000000000",
+                "ft_transfer": "10000000000000",
+                "withdraw_callback": "10000000000000"
+            }
+        }))
+        .transact()
+        .await?;
+    assert!(res_proposal_gas.is_success(), "Updating the proposal gas config failed");
+
+    // A proposal at the default size limits
+    let options: Vec<String> = (0..10).map(|index| format!("{:0>64}", index)).collect();
+    let now = sandbox.view_block().await?.timestamp();
+    let deadline = now + 60 * 1_000_000_000;
+    let create_proposal_outcome = root_account
+        .call(dao_contract.id(), "create_proposal")
+        .args_json(json!({
+            "title": "t".repeat(128),
+            "description": "d".repeat(4096),
+            "deadline": deadline,
+            "options_vec": options,
+            "minimum_votes": 1
+        }))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(create_proposal_outcome.is_success(), "Creating a large proposal failed");
+    let proposal_id: u64 = create_proposal_outcome.json()?;
+
+    let res_vote = root_account
+        .call(proposal_contract.id(), "vote")
+        .args_json(json!({
+            "proposal_id": proposal_id,
+            "voter": root_account.id(),
+            "vote_option": 9
+        }))
+        .deposit(NearToken::from_millinear(10))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res_vote.is_success(), "Voting on a large proposal failed");
+    assert!(res_vote.receipt_failures().is_empty(), "A vote receipt failed");
+
+    while sandbox.view_block().await?.timestamp() <= deadline {
+        sandbox.fast_forward(1_000).await?;
+    }
+    let res_finalize = root_account
+        .call(dao_contract.id(), "finalize_proposal")
+        .args_json(json!({ "proposal_id": proposal_id }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res_finalize.is_success(), "Finalizing a large proposal failed");
+    let final_state: String = res_finalize.json()?;
+    assert_eq!(final_state, "Passed");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_membership_voting() -> anyhow::Result<()> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let dao_contract_wasm = near_workspaces::compile_project("./dao-contract").await?;
+    let proposal_contract_wasm = near_workspaces::compile_project("./proposal-contract").await?;
+    let dao_contract = sandbox.dev_deploy(&dao_contract_wasm).await?;
+    let proposal_contract = sandbox.dev_deploy(&proposal_contract_wasm).await?;
+    let root_account = sandbox.dev_create_account().await?;
+    let member_account = sandbox.dev_create_account().await?;
+    let outsider_account = sandbox.dev_create_account().await?;
+
+    // No token contract, eligibility comes from the DAO's member registry
+    let _proposal_contract_result = proposal_contract
+        .call("new")
+        .args_json(json!({ "token_contract_id": dao_contract.id() }))
+        .transact()
+        .await?;
+    let _source_result = proposal_contract
+        .call("set_voting_power_source")
+        .args_json(json!({ "source": { "Membership": { "dao_contract_id": dao_contract.id() } } }))
+        .transact()
+        .await?;
+    let _dao_contract_result = dao_contract
+        .call("new")
+        .args_json(json!({
+            "admin_account_id": root_account.id(),
+            "proposal_contract_id": proposal_contract.id()
+        }))
+        .transact()
+        .await?;
+
+    // The admin proposes the first member, later changes need the approval of a majority of the members
+    let now = sandbox.view_block().await?.timestamp();
+    let res_add_member = root_account
+        .call(dao_contract.id(), "propose_membership_change")
+        .args_json(json!({
+            "change": { "Add": { "account_id": member_account.id(), "metadata": "Working group lead" } },
+            "deadline": now + 60 * 1_000_000_000
+        }))
+        .deposit(NearToken::from_millinear(30))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res_add_member.is_success(), "Adding the member failed");
+    let is_member: bool = dao_contract
+        .call("is_member")
+        .args_json(json!({ "account_id": member_account.id() }))
+        .view()
+        .await?
+        .json()?;
+    assert!(is_member);
+
+    let now = sandbox.view_block().await?.timestamp();
+    let create_proposal_outcome = root_account
+        .call(dao_contract.id(), "create_proposal")
+        .args_json(json!({
+            "title": "Proposal 1",
+            "description": "Description here",
+            "deadline": now + 60 * 1_000_000_000,
+            "options_vec": ["Yes", "No"],
+            "minimum_votes": 1
+        }))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    let proposal_id: u64 = create_proposal_outcome.json()?;
+
+    let res_member_vote = member_account
+        .call(proposal_contract.id(), "vote")
+        .args_json(json!({ "proposal_id": proposal_id, "voter": member_account.id(), "vote_option": 0 }))
+        .deposit(NearToken::from_millinear(10))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res_member_vote.is_success(), "Member vote failed");
+
+    let res_outsider_vote = outsider_account
+        .call(proposal_contract.id(), "vote")
+        .args_json(json!({ "proposal_id": proposal_id, "voter": outsider_account.id(), "vote_option": 1 }))
+        .deposit(NearToken::from_millinear(10))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res_outsider_vote.is_failure(), "Non-member vote should fail");
+
+    let votes: Vec<(String, u64)> = proposal_contract
+        .call("get_votes")
+        .args_json(json!({ "proposal_id": proposal_id }))
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(votes, vec![("Yes".to_string(), 1), ("No".to_string(), 0)]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_nft_voting() -> anyhow::Result<()> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let proposal_contract_wasm = near_workspaces::compile_project("./proposal-contract").await?;
+    let nft_contract_wasm = near_workspaces::compile_project("./tests/mock-nft").await?;
+    let proposal_contract = sandbox.dev_deploy(&proposal_contract_wasm).await?;
+    let nft_contract = sandbox.dev_deploy(&nft_contract_wasm).await?;
+    let holder_account = sandbox.dev_create_account().await?;
+    let outsider_account = sandbox.dev_create_account().await?;
+
+    let _nft_contract_result = nft_contract.call("new").transact().await?;
+    let _mint_result = nft_contract
+        .call("mint")
+        .args_json(json!({ "owner_id": holder_account.id(), "count": 2 }))
+        .transact()
+        .await?;
+    let _proposal_contract_result = proposal_contract
+        .call("new")
+        .args_json(json!({ "token_contract_id": nft_contract.id() }))
+        .transact()
+        .await?;
+    let _source_result = proposal_contract
+        .call("set_voting_power_source")
+        .args_json(json!({ "source": { "NftCount": { "nft_contract_id": nft_contract.id() } } }))
+        .transact()
+        .await?;
+
+    let now = sandbox.view_block().await?.timestamp();
+    let create_proposal_outcome = holder_account
+        .call(proposal_contract.id(), "create_proposal")
+        .args_json(json!({
+            "title": "Proposal 1",
+            "description": "Description here",
+            "deadline": now + 60 * 1_000_000_000,
+            "options_vec": ["Yes", "No"],
+            "minimum_votes": 1
+        }))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    let proposal_id: u64 = create_proposal_outcome.json()?;
+
+    let res_holder_vote = holder_account
+        .call(proposal_contract.id(), "vote")
+        .args_json(json!({ "proposal_id": proposal_id, "voter": holder_account.id(), "vote_option": 0 }))
+        .deposit(NearToken::from_millinear(10))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res_holder_vote.is_success(), "NFT holder vote failed");
+
+    let res_outsider_vote = outsider_account
+        .call(proposal_contract.id(), "vote")
+        .args_json(json!({ "proposal_id": proposal_id, "voter": outsider_account.id(), "vote_option": 1 }))
+        .deposit(NearToken::from_millinear(10))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res_outsider_vote.is_failure(), "Vote without NFTs should fail");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_token_weighted_voting() -> anyhow::Result<()> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let proposal_contract_wasm = near_workspaces::compile_project("./proposal-contract").await?;
+    let token_contract_wasm = near_workspaces::compile_project("./token-contract").await?;
+    let proposal_contract = sandbox.dev_deploy(&proposal_contract_wasm).await?;
+    let fdao_contract = sandbox.dev_deploy(&token_contract_wasm).await?;
+    let lp_contract = sandbox.dev_deploy(&token_contract_wasm).await?;
+    let voter_account = sandbox.dev_create_account().await?;
+
+    // 1000 FDAO and 10 LP tokens, both with 24 decimals
+    for (token_contract, total_supply) in [
+        (&fdao_contract, "1000000000000000000000000000"),
+        (&lp_contract, "10000000000000000000000000"),
+    ] {
+        let _token_contract_result = token_contract
+            .call("new_default_meta")
+            .args_json(json!({ "owner_id": voter_account.id(), "total_supply": total_supply }))
+            .transact()
+            .await?;
+    }
+    let _proposal_contract_result = proposal_contract
+        .call("new")
+        .args_json(json!({ "token_contract_id": fdao_contract.id() }))
+        .transact()
+        .await?;
+
+    let now = sandbox.view_block().await?.timestamp();
+    let create_proposal_outcome = voter_account
+        .call(proposal_contract.id(), "create_proposal")
+        .args_json(json!({
+            "title": "Proposal 1",
+            "description": "Description here",
+            "deadline": now + 60 * 1_000_000_000,
+            "options_vec": ["Yes", "No"],
+            "minimum_votes": 1,
+            "voting_weight": { "TokenWeighted": { "sources": [
+                { "token_contract_id": fdao_contract.id(), "decimals": 24, "multiplier": 1 },
+                { "token_contract_id": lp_contract.id(), "decimals": 24, "multiplier": 3 }
+            ] } }
+        }))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    let proposal_id: u64 = create_proposal_outcome.json()?;
+
+    let res_vote = voter_account
+        .call(proposal_contract.id(), "vote")
+        .args_json(json!({ "proposal_id": proposal_id, "voter": voter_account.id(), "vote_option": 0 }))
+        .deposit(NearToken::from_millinear(10))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res_vote.is_success(), "Weighted vote failed");
+
+    let votes: Vec<(String, u64)> = proposal_contract
+        .call("get_votes")
+        .args_json(json!({ "proposal_id": proposal_id }))
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(votes, vec![("Yes".to_string(), 1030), ("No".to_string(), 0)]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_conviction_proposal_passes() -> anyhow::Result<()> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let proposal_contract_wasm = near_workspaces::compile_project("./proposal-contract").await?;
+    let token_contract_wasm = near_workspaces::compile_project("./token-contract").await?;
+    let proposal_contract = sandbox.dev_deploy(&proposal_contract_wasm).await?;
+    let token_contract = sandbox.dev_deploy(&token_contract_wasm).await?;
+    let root_account = sandbox.dev_create_account().await?;
+    let grantee_account = sandbox.dev_create_account().await?;
+
+    let _token_contract_result = token_contract
+        .call("new_default_meta")
+        .args_json(json!({
+            "owner_id": root_account.id(),
+            "total_supply": "1000000000000000000000000"
+        }))
+        .transact()
+        .await?;
+    let _proposal_contract_result = proposal_contract
+        .call("new")
+        .args_json(json!({ "token_contract_id": token_contract.id() }))
+        .transact()
+        .await?;
+    // The proposal contract holds the stakes
+    let _storage_result = token_contract
+        .call("storage_deposit")
+        .args_json(json!({ "account_id": proposal_contract.id(), "registration_only": true }))
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    // One second periods and the root account as the treasury
+    let _config_result = proposal_contract
+        .call("set_conviction_config")
+        .args_json(json!({
+            "conviction_config": {
+                "period": 1_000_000_000u64,
+                "decay": 800_000_000u32,
+                "max_ratio": 200_000_000u32,
+                "weight": 2_500_000u32,
+                "treasury_account_id": root_account.id()
+            }
+        }))
+        .transact()
+        .await?;
+    let res_treasury = root_account
+        .call(proposal_contract.id(), "refresh_treasury")
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res_treasury.is_success(), "Treasury refresh failed");
+    let res_supply = root_account
+        .call(proposal_contract.id(), "refresh_token_supply")
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res_supply.is_success(), "Token supply refresh failed");
+
+    let create_outcome = root_account
+        .call(proposal_contract.id(), "create_conviction_proposal")
+        .args_json(json!({
+            "title": "Grant",
+            "description": "Fund the docs rewrite",
+            "beneficiary": grantee_account.id(),
+            "requested_amount": "10000000000000000000000"
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?;
+    let proposal_id: u64 = create_outcome.json()?;
+
+    // The staker pays for the storage of their stakes up front
+    let res_stake_storage = root_account
+        .call(proposal_contract.id(), "deposit_stake_storage")
+        .deposit(NearToken::from_millinear(10))
+        .transact()
+        .await?;
+    assert!(res_stake_storage.is_success(), "Stake storage deposit failed");
+    let res_stake = root_account
+        .call(token_contract.id(), "ft_transfer_call")
+        .args_json(json!({
+            "receiver_id": proposal_contract.id(),
+            "amount": "500000000000000000000000",
+            "msg": json!({ "proposal_id": proposal_id }).to_string()
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res_stake.is_success(), "Staking failed");
+
+    let stake: String = proposal_contract
+        .call("get_conviction_stake")
+        .args_json(json!({ "proposal_id": proposal_id, "account_id": root_account.id() }))
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(stake, "500000000000000000000000");
+
+    // Half of the supply staked for a few seconds is enough for a 1% request
+    sandbox.fast_forward(10).await?;
+    let res_update = root_account
+        .call(proposal_contract.id(), "update_conviction")
+        .args_json(json!({ "proposal_id": proposal_id }))
+        .transact()
+        .await?;
+    let state: String = res_update.json()?;
+    assert_eq!(state, "Passed");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_optimistic_proposal_escalates() -> anyhow::Result<()> {
+    let sandbox = near_workspaces::sandbox().await?;
+
+    let proposal_contract_wasm = near_workspaces::compile_project("./proposal-contract").await?;
+    let token_contract_wasm = near_workspaces::compile_project("./token-contract").await?;
+    let proposal_contract = sandbox.dev_deploy(&proposal_contract_wasm).await?;
+    let token_contract = sandbox.dev_deploy(&token_contract_wasm).await?;
+    let root_account = sandbox.dev_create_account().await?;
+
+    let _token_contract_result = token_contract
+        .call("new_default_meta")
+        .args_json(json!({
+            "owner_id": root_account.id(),
+            "total_supply": "1000000000000000000000000"
+        }))
+        .transact()
+        .await?;
+    let _proposal_contract_result = proposal_contract
+        .call("new")
+        .args_json(json!({ "token_contract_id": token_contract.id() }))
+        .transact()
+        .await?;
+
+    let now = sandbox.view_block().await?.timestamp();
+    let mut proposal_ids = Vec::new();
+    for title in ["Routine payout", "Contested payout"] {
+        let create_proposal_outcome = root_account
+            .call(proposal_contract.id(), "create_proposal")
+            .args_json(json!({
+                "title": title,
+                "description": "Description here",
+                "deadline": now + 60 * 1_000_000_000,
+                "options_vec": ["Approve", "Reject"],
+                "minimum_votes": 1,
+                "voting_mode": "Optimistic"
+            }))
+            .deposit(NearToken::from_near(1))
+            .max_gas()
+            .transact()
+            .await?;
+        proposal_ids.push(create_proposal_outcome.json::<u64>()?);
+    }
+
+    // Lower the threshold so that the root account's single FDAO escalates
+    let _config_result = proposal_contract
+        .call("set_optimistic_config")
+        .args_json(json!({
+            "optimistic_config": {
+                "objection_threshold": "1",
+                "escalation_period": 3_600_000_000_000u64
+            }
+        }))
+        .transact()
+        .await?;
+    // Objections lock FDAO on the proposal contract, the objector pays for their storage up front
+    let _storage_result = token_contract
+        .call("storage_deposit")
+        .args_json(json!({ "account_id": proposal_contract.id(), "registration_only": true }))
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    let res_stake_storage = root_account
+        .call(proposal_contract.id(), "deposit_stake_storage")
+        .deposit(NearToken::from_millinear(10))
+        .transact()
+        .await?;
+    assert!(res_stake_storage.is_success(), "Stake storage deposit failed");
+    let res_object = root_account
+        .call(token_contract.id(), "ft_transfer_call")
+        .args_json(json!({
+            "receiver_id": proposal_contract.id(),
+            "amount": "1000000000000000000000000",
+            "msg": json!({ "object_to": proposal_ids[1] }).to_string()
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res_object.is_success(), "Objection failed");
+    let escalated_at: Option<u64> = proposal_contract
+        .call("get_escalated_at")
+        .args_json(json!({ "proposal_id": proposal_ids[1] }))
+        .view()
+        .await?
+        .json()?;
+    assert!(escalated_at.is_some());
+
+    while sandbox.view_block().await?.timestamp() <= now + 60 * 1_000_000_000 {
+        sandbox.fast_forward(1_000).await?;
+    }
+    let res_finalize = root_account
+        .call(proposal_contract.id(), "update_status")
+        .args_json(json!({ "proposal_id": proposal_ids[0] }))
+        .transact()
+        .await?;
+    let state: String = res_finalize.json()?;
+    assert_eq!(state, "Passed");
+
+    // The escalated proposal now runs a regular vote with a later deadline
+    let res_finalize_escalated = root_account
+        .call(proposal_contract.id(), "update_status")
+        .args_json(json!({ "proposal_id": proposal_ids[1] }))
+        .transact()
+        .await?;
+    assert!(res_finalize_escalated.is_failure(), "The escalated vote should still be running");
+
+    Ok(())
+}
@@ -0,0 +1,35 @@
+// Only implements the parts of NEP-171 the proposal contract reads
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::collections::LookupMap;
+use near_sdk::json_types::U128;
+use near_sdk::{near_bindgen, AccountId, BorshStorageKey, PanicOnDefault};
+
+#[derive(BorshSerialize, BorshStorageKey)]
+#[borsh(crate = "near_sdk::borsh")]
+enum StorageKey {
+    TokensPerOwner,
+}
+
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+pub struct MockNft {
+    tokens_per_owner: LookupMap<AccountId, u128>,
+}
+
+#[near_bindgen]
+impl MockNft {
+    #[init]
+    pub fn new() -> Self {
+        Self { tokens_per_owner: LookupMap::new(StorageKey::TokensPerOwner) }
+    }
+
+    // Anyone can mint, the count is all the tests need
+    pub fn mint(&mut self, owner_id: AccountId, count: u64) {
+        let supply = self.tokens_per_owner.get(&owner_id).unwrap_or(0);
+        self.tokens_per_owner.insert(&owner_id, &(supply + count as u128));
+    }
+
+    pub fn nft_supply_for_owner(&self, account_id: AccountId) -> U128 {
+        U128(self.tokens_per_owner.get(&account_id).unwrap_or(0))
+    }
+}
@@ -14,11 +14,18 @@ use near_sdk::{
     env, log, near, require, AccountId, BorshStorageKey, NearToken, PanicOnDefault, PromiseOrValue,
 };
 
+mod pause;
+
 #[derive(PanicOnDefault)]
 #[near(contract_state)]
 pub struct Contract {
     token: FungibleToken,
     metadata: LazyOption<FungibleTokenMetadata>,
+    /// Governance account, usually the DAO, the only one that can unpause methods.
+    governance_id: AccountId,
+    /// Account that can pause methods in an emergency.
+    guardian: Option<AccountId>,
+    paused_methods: Vec<String>,
 }
 
 const DATA_IMAGE_SVG_NEAR_ICON: &str = "data:image/svg+xml;base64,PHN2ZyBoZWlnaHQ9IjgwMCIgd2lkdGg9IjgwMCIgeG1sbnM9Imh0dHA6Ly93d3cudzMub3JnLzIwMDAvc3ZnIiB2aWV3Qm94PSIwIDAgMjk2LjQ3MyAyOTYuNDczIiB4bWw6c3BhY2U9InByZXNlcnZlIj48cGF0aCBkPSJNMTQ4LjIzNyAwQzY2LjM2OCAwIC4wMDEgNjYuMzY3LjAwMSAxNDguMjM2czY2LjM2NyAxNDguMjM2IDE0OC4yMzYgMTQ4LjIzNmM4MS44NjcgMCAxNDguMjM0LTY2LjM2NyAxNDguMjM0LTE0OC4yMzZTMjMwLjEwNCAwIDE0OC4yMzcgMHptNzMuODM4IDM4LjQ5NS05LjQ0NiAxMy45NjJhMTE0Ljc0MiAxMTQuNzQyIDAgMCAwLTQyLjMxOS0xNy41MDZsMy4zMTQtMTYuNTI1YTEzMC42NzkgMTMwLjY3OSAwIDAgMSA0OC40NTEgMjAuMDY5em0tNjYuMTcyIDE3NS41M3YxNy4yMTFoLTE0di0xNy4xMjRjLTEwLTEuMDk5LTE5LjM4NC00LjkzLTI2LjQ0LTExLjQ5NS03LjA1OC02LjU2NC0xMC4yODYtMTYuMjE1LTEwLjA1NC0yOC45NDFsLjM1OC0uNDM5aDI0LjcyNmMwIDggMS43NzkgMTMuMzExIDUuMzM2IDE2LjY2NCAzLjU1OCAzLjM1NSA4LjA4MiA1LjAzNSAxMy41NzggNS4wMzUgNS4yNjMgMCA5LjI5Ny0xLjQgMTIuMTAyLTQuMjA3IDIuODA1LTIuODA1IDQuMjA4LTYuNTc3IDQuMjA4LTExLjMyIDAtNC42ODYtMS4zNDUtOC41MDItNC4wMzQtMTEuNDUxLTIuNjg5LTIuOTUtNy4zMDMtNS42OTYtMTMuODM4LTguMjQyLTEyLjg0MS00LjY4Ni0yMi4zNzEtOS45MDQtMjguNTg4LTE1LjY2LTYuMjE4LTUuNzU1LTkuMzI2LTEzLjg2Ny05LjMyNi0yNC4zMzYgMC05LjcxOCAyLjg3My0xNy42ODQgOS4wODktMjMuOTAyIDYuMjE3LTYuMjE3IDE0Ljg4MS05Ljg3NiAyNC44ODEtMTAuOTc2VjY2LjIzNmgxNHYxOC43NzhjMTAgMS41MDUgMTguMzEgNS43NTYgMjQuMTIzIDEyLjc1NCA1LjgxMyA2Ljk5OSA4LjcyOCAxNS45NzYgOC42MTMgMjcuMTM4bC0uMTkzLjMzaC0yNC43MjdjMC03LTEuNDAzLTExLjk4NC00LjIwOC0xNS42ODYtMi44MDYtMy43MDEtNi42MzctNS41MDEtMTEuNDk1LTUuNTAxLTQuOTc2IDAtOC42NzYgMS40NTctMTEuMTA1IDQuMzItMi40MyAyLjg2My0zLjY0MyA2LjY4MS0zLjY0MyAxMS40MjMgMCA0LjUxMSAxLjI4NiA4LjE3NiAzLjg2IDEwLjk4MSAyLjU3MyAyLjgwNiA3LjMwMiA1LjU3MSAxNC4xODYgOC4yODkgMTIuNzgyIDUuMDMyIDIyLjI1NCAxMC40MTIgMjguNDEzIDE2LjEzOSA2LjE2IDUuNzI2IDkuMjQgMTMuNzM4IDkuMjQgMjQuMDMzIDAgMTAuMDY0LTMuMzYxIDE4LjEwNS05LjU0OSAyNC4xMi02LjE4OSA2LjAxNy0xNC41MTMgOS41NzMtMjUuNTEzIDEwLjY3MXpNMTIyLjg1IDE4LjQyNmwzLjE5MiAxNi41NDlhMTE0Ljc1OCAxMTQuNzU4IDAgMCAwLTQyLjMgMTcuNTVsLTkuMzQzLTE0LjAzYTEzMC42NzIgMTMwLjY3MiAwIDAgMSA0OC40NTEtMjAuMDY5ek0zOC40OTYgNzQuMzk3bDEzLjk2MiA5LjQ0NmExMTQuNzM1IDExNC43MzUgMCAwIDAtMTcuNTA1IDQyLjMxOGwtMTYuNTI2LTMuMzE0YTEzMC43MTUgMTMwLjcxNSAwIDAgMSAyMC4wNjktNDguNDV6bS0uMDAxIDE0Ny42NzZhMTMwLjY3NyAxMzAuNjc3IDAgMCAxLTIwLjA2OC00OC40NTFsMTYuNTQ5LTMuMTkyYTExNC43NjIgMTE0Ljc2MiAwIDAgMCAxNy41NSA0Mi4zMDFsLTE0LjAzMSA5LjM0MnptMzUuOTAyIDM1LjkwMyA5LjQ0Ni0xMy45NjJhMTE0LjcyNCAxMTQuNzI0IDAgMCAwIDQyLjMyIDE3LjUwN2wtMy4zMTQgMTYuNTI2YTEzMC42OSAxMzAuNjkgMCAwIDEtNDguNDUyLTIwLjA3MXptOTkuMjI2IDIwLjA3MS0zLjE5Mi0xNi41NDlhMTE0Ljc1MiAxMTQuNzUyIDAgMCAwIDQyLjMwMi0xNy41NTFsOS4zNDMgMTQuMDNhMTMwLjY4IDEzMC42OCAwIDAgMS00OC40NTMgMjAuMDd6bTg0LjM1NS01NS45NzMtMTMuOTYyLTkuNDQ2YTExNC43NDcgMTE0Ljc0NyAwIDAgMCAxNy41MDUtNDIuMzE5bDE2LjUyNSAzLjMxNGExMzAuNzEgMTMwLjcxIDAgMCAxLTIwLjA2OCA0OC40NTF6bTMuNTE5LTk2LjAzM2ExMTQuNzU5IDExNC43NTkgMCAwIDAtMTcuNTUxLTQyLjMwMmwxNC4wMy05LjM0M2ExMzAuNjgzIDEzMC42ODMgMCAwIDEgMjAuMDY5IDQ4LjQ1MmwtMTYuNTQ4IDMuMTkzeiIvPjwvc3ZnPg==";
@@ -60,6 +67,9 @@ impl Contract {
         let mut this = Self {
             token: FungibleToken::new(StorageKey::FungibleToken),
             metadata: LazyOption::new(StorageKey::Metadata, Some(&metadata)),
+            governance_id: owner_id.clone(),
+            guardian: None,
+            paused_methods: Vec::new(),
         };
         this.token.internal_register_account(&owner_id);
         this.token.internal_deposit(&owner_id, total_supply.into());
@@ -79,6 +89,7 @@ impl Contract {
 impl FungibleTokenCore for Contract {
     #[payable]
     fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        self.assert_not_paused("ft_transfer");
         self.token.ft_transfer(receiver_id, amount, memo)
     }
 
@@ -90,6 +101,7 @@ impl FungibleTokenCore for Contract {
         memo: Option<String>,
         msg: String,
     ) -> PromiseOrValue<U128> {
+        self.assert_not_paused("ft_transfer_call");
         self.token.ft_transfer_call(receiver_id, amount, memo, msg)
     }
 
@@ -128,16 +140,19 @@ impl StorageManagement for Contract {
         account_id: Option<AccountId>,
         registration_only: Option<bool>,
     ) -> StorageBalance {
+        self.assert_not_paused("storage_deposit");
         self.token.storage_deposit(account_id, registration_only)
     }
 
     #[payable]
     fn storage_withdraw(&mut self, amount: Option<NearToken>) -> StorageBalance {
+        self.assert_not_paused("storage_withdraw");
         self.token.storage_withdraw(amount)
     }
 
     #[payable]
     fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        self.assert_not_paused("storage_unregister");
         #[allow(unused_variables)]
         if let Some((account_id, balance)) = self.token.internal_storage_unregister(force) {
             log!("Closed @{} with {}", account_id, balance);
@@ -186,7 +201,7 @@ mod tests {
     fn test_new() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
-        let contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        let contract = Contract::new_default_meta(accounts(1), TOTAL_SUPPLY.into());
         testing_env!(context.is_view(true).build());
         assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
         assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY);
@@ -204,10 +219,10 @@ mod tests {
     fn test_transfer() {
         let mut context = get_context(accounts(2));
         testing_env!(context.build());
-        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        let mut contract = Contract::new_default_meta(accounts(2), TOTAL_SUPPLY.into());
         testing_env!(context
             .storage_usage(env::storage_usage())
-            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .attached_deposit(contract.storage_balance_bounds().min)
             .predecessor_account_id(accounts(1))
             .build());
         // Paying for account registration, aka storage deposit
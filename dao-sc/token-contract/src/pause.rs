@@ -0,0 +1,189 @@
+use near_contract_standards::fungible_token::metadata::FungibleTokenMetadata;
+use near_contract_standards::fungible_token::FungibleToken;
+use near_sdk::collections::LazyOption;
+use near_sdk::{env, log, near, require, AccountId, PanicOnDefault};
+
+use crate::{Contract, ContractExt};
+
+/// State layout of the contract before the pause was added, read by `migrate`.
+#[derive(PanicOnDefault)]
+#[near]
+struct ContractWithoutPause {
+    token: FungibleToken,
+    metadata: LazyOption<FungibleTokenMetadata>,
+}
+
+/// Methods the guardian can pause.
+pub const PAUSABLE_METHODS: [&str; 5] = [
+    "ft_transfer",
+    "ft_transfer_call",
+    "storage_deposit",
+    "storage_withdraw",
+    "storage_unregister",
+];
+
+#[near]
+impl Contract {
+    /// Upgrades a contract deployed before the pause was added. The existing state has no
+    /// governance account, so one is given here, usually the DAO. Contracts initialized with
+    /// `new` already have the current layout and do not need it.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate(governance_id: AccountId) -> Self {
+        let old: ContractWithoutPause = env::state_read().expect("No state to migrate");
+        log!("Migrated with governance {}", governance_id);
+        Self {
+            token: old.token,
+            metadata: old.metadata,
+            governance_id,
+            guardian: None,
+            paused_methods: Vec::new(),
+        }
+    }
+
+    /// Hands the governance role to another account, such as the DAO once it is deployed.
+    /// `new` gives it to the initial token owner.
+    pub fn set_governance_id(&mut self, governance_id: AccountId) {
+        self.assert_governance();
+        log!("Governance set to {}", governance_id);
+        self.governance_id = governance_id;
+    }
+
+    pub fn get_governance_id(&self) -> AccountId {
+        self.governance_id.clone()
+    }
+
+    /// Sets the account allowed to pause methods, only governance can call it.
+    pub fn set_guardian(&mut self, guardian: Option<AccountId>) {
+        self.assert_governance();
+        log!("Guardian set to {:?}", guardian);
+        self.guardian = guardian;
+    }
+
+    pub fn get_guardian(&self) -> Option<AccountId> {
+        self.guardian.clone()
+    }
+
+    /// Pauses the given methods. The guardian can only pause, unpausing is left to governance.
+    pub fn pause(&mut self, methods: Vec<String>) {
+        let caller = env::predecessor_account_id();
+        require!(
+            self.guardian.as_ref() == Some(&caller) || caller == self.governance_id,
+            "Only the guardian can pause"
+        );
+        for method in methods {
+            require!(
+                PAUSABLE_METHODS.contains(&method.as_str()),
+                format!("Unknown pausable method: {}", method)
+            );
+            if !self.paused_methods.contains(&method) {
+                log!("Method {} paused by {}", method, caller);
+                self.paused_methods.push(method);
+            }
+        }
+    }
+
+    pub fn unpause(&mut self, methods: Vec<String>) {
+        self.assert_governance();
+        for method in methods {
+            log!("Method {} unpaused", method);
+            self.paused_methods.retain(|paused| paused != &method);
+        }
+    }
+
+    pub fn get_paused_methods(&self) -> Vec<String> {
+        self.paused_methods.clone()
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_not_paused(&self, method: &str) {
+        require!(
+            !self.paused_methods.iter().any(|paused| paused == method),
+            format!("Method {} is paused", method)
+        );
+    }
+
+    fn assert_governance(&self) {
+        require!(
+            env::predecessor_account_id() == self.governance_id,
+            "Only governance can call this method"
+        );
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_contract_standards::fungible_token::FungibleTokenCore;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, NearToken};
+
+    use super::*;
+
+    fn setup_contract() -> (VMContextBuilder, Contract) {
+        let mut context = VMContextBuilder::new();
+        context.current_account_id(accounts(0)).predecessor_account_id(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1), 1_000_000.into());
+        contract.set_guardian(Some(accounts(3)));
+        (context, contract)
+    }
+
+    #[test]
+    #[should_panic(expected = "Method ft_transfer is paused")]
+    fn test_paused_transfer() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.pause(vec!["ft_transfer".to_string()]);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.ft_transfer(accounts(2), 10.into(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only governance can call this method")]
+    fn test_guardian_cannot_unpause() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.pause(vec!["ft_transfer".to_string()]);
+        contract.unpause(vec!["ft_transfer".to_string()]);
+    }
+
+    #[test]
+    fn test_dao_unpauses() {
+        let (mut context, mut contract) = setup_contract();
+        contract.set_governance_id(accounts(4));
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.pause(vec!["ft_transfer".to_string(), "storage_deposit".to_string()]);
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.unpause(vec!["ft_transfer".to_string()]);
+        assert_eq!(contract.get_paused_methods(), vec!["storage_deposit".to_string()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only governance can call this method")]
+    fn test_owner_cannot_unpause_after_handover() {
+        let (mut context, mut contract) = setup_contract();
+        contract.set_governance_id(accounts(4));
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.unpause(vec!["ft_transfer".to_string()]);
+    }
+
+    #[test]
+    fn test_migrate_keeps_balances() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.current_account_id(accounts(0)).predecessor_account_id(accounts(0)).build());
+        let mut token = FungibleToken::new(b"t".to_vec());
+        token.internal_register_account(&accounts(1));
+        token.internal_deposit(&accounts(1), 500);
+        env::state_write(&ContractWithoutPause { token, metadata: LazyOption::new(b"m".to_vec(), None) });
+
+        let contract = Contract::migrate(accounts(4));
+        assert_eq!(contract.ft_balance_of(accounts(1)), 500.into());
+        assert_eq!(contract.get_governance_id(), accounts(4));
+        assert!(contract.get_paused_methods().is_empty());
+    }
+}